@@ -0,0 +1,204 @@
+// Copyright (C) 2023 Nydus Developers. All rights reserved.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Content-defined chunking (FastCDC, gear-hash variant).
+//!
+//! Fixed-size chunking cuts a file every `chunk_size` bytes, so inserting a single byte near the
+//! front of a file shifts every later chunk boundary and defeats dedup against unrelated layers
+//! sharing most of that file's content. FastCDC instead picks boundaries based on a rolling
+//! fingerprint of the content itself, so unaffected regions of an edited file keep producing the
+//! same chunks.
+
+/// Size of the gear hash table.
+const GEAR_SIZE: usize = 256;
+
+/// Normalization level: how many bits the strict/loose masks differ from the bit width implied by
+/// `avg_size`, as described by the FastCDC paper's normalized chunking.
+const NORMALIZATION_LEVEL: u32 = 2;
+
+/// Table of pseudo-random `u64` values used to roll the gear-hash fingerprint, one entry per
+/// possible byte value. Generated at compile time with a SplitMix64-style generator so it's both
+/// deterministic (reproducible chunking across builds) and statistically well distributed.
+static GEAR: [u64; GEAR_SIZE] = build_gear_table();
+
+const fn build_gear_table() -> [u64; GEAR_SIZE] {
+    let mut table = [0u64; GEAR_SIZE];
+    let mut seed: u64 = 0x9E3779B97F4A7C15;
+    let mut i = 0;
+    while i < GEAR_SIZE {
+        seed = seed.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        table[i] = z;
+        i += 1;
+    }
+    table
+}
+
+/// Minimum, average and maximum chunk sizes driving the content-defined cut points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FastCdcParams {
+    pub min_size: u32,
+    pub avg_size: u32,
+    pub max_size: u32,
+}
+
+impl Default for FastCdcParams {
+    /// 256 KiB / 1 MiB / 4 MiB, as commonly used for FastCDC over container layers.
+    fn default() -> Self {
+        Self {
+            min_size: 256 * 1024,
+            avg_size: 1024 * 1024,
+            max_size: 4 * 1024 * 1024,
+        }
+    }
+}
+
+/// Cuts a byte stream into variable-length, content-defined chunks.
+pub struct FastCdcChunker {
+    params: FastCdcParams,
+    mask_s: u64,
+    mask_l: u64,
+}
+
+impl FastCdcChunker {
+    /// Create a new chunker for the given size parameters.
+    pub fn new(params: FastCdcParams) -> Self {
+        let bits = (params.avg_size as f64).log2().round() as u32;
+        let mask_s = (1u64 << (bits + NORMALIZATION_LEVEL)) - 1;
+        let mask_l = (1u64 << bits.saturating_sub(NORMALIZATION_LEVEL)) - 1;
+
+        FastCdcChunker {
+            params,
+            mask_s,
+            mask_l,
+        }
+    }
+
+    /// Find the length of the next chunk at the start of `data`.
+    ///
+    /// Bytes `[0, min_size)` are always skipped (never cut there). From `min_size` up to
+    /// `avg_size` the stricter `mask_s` (more one-bits, so harder to satisfy) is used, biasing the
+    /// cut point towards the average; beyond `avg_size` up to `max_size` the looser `mask_l` is
+    /// used so a cut becomes more likely the longer the chunk grows. A cut is always forced at
+    /// `max_size`.
+    pub fn next_cut_point(&self, data: &[u8]) -> usize {
+        let min_size = self.params.min_size as usize;
+        let avg_size = self.params.avg_size as usize;
+        let max_size = self.params.max_size as usize;
+        let len = data.len();
+
+        if len <= min_size {
+            return len;
+        }
+
+        let end = std::cmp::min(len, max_size);
+        let mut fp: u64 = 0;
+        let mut i = min_size;
+        while i < end {
+            fp = (fp << 1).wrapping_add(GEAR[data[i] as usize]);
+            let mask = if i < avg_size { self.mask_s } else { self.mask_l };
+            if fp & mask == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+
+        end
+    }
+
+    /// Split `data` into `(offset, len)` chunk boundaries covering the whole buffer.
+    pub fn chunks(&self, data: &[u8]) -> Vec<(usize, usize)> {
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+
+        while offset < data.len() {
+            let len = self.next_cut_point(&data[offset..]);
+            if len == 0 {
+                break;
+            }
+            chunks.push((offset, len));
+            offset += len;
+        }
+
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_input_produces_no_chunks() {
+        let chunker = FastCdcChunker::new(FastCdcParams::default());
+        assert!(chunker.chunks(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_small_input_is_a_single_chunk() {
+        let chunker = FastCdcChunker::new(FastCdcParams::default());
+        let data = vec![0u8; 1024];
+        let chunks = chunker.chunks(&data);
+        assert_eq!(chunks, vec![(0, 1024)]);
+    }
+
+    #[test]
+    fn test_chunks_cover_the_whole_input_and_respect_max_size() {
+        let params = FastCdcParams {
+            min_size: 16,
+            avg_size: 64,
+            max_size: 256,
+        };
+        let chunker = FastCdcChunker::new(params);
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+
+        let chunks = chunker.chunks(&data);
+        let total: usize = chunks.iter().map(|&(_, len)| len).sum();
+        assert_eq!(total, data.len());
+        for &(_, len) in &chunks {
+            assert!(len <= params.max_size as usize);
+        }
+    }
+
+    #[test]
+    fn test_content_defined_cuts_are_stable_across_a_prefix_insertion() {
+        let params = FastCdcParams {
+            min_size: 16,
+            avg_size: 64,
+            max_size: 256,
+        };
+        let chunker = FastCdcChunker::new(params);
+        let tail: Vec<u8> = (0..5_000u32).map(|i| (i % 197) as u8).collect();
+
+        let mut original = vec![1u8; 100];
+        original.extend_from_slice(&tail);
+
+        let mut edited = vec![1u8; 101];
+        edited.extend_from_slice(&tail);
+
+        let original_chunks: Vec<&[u8]> = chunker
+            .chunks(&original)
+            .into_iter()
+            .map(|(o, l)| &original[o..o + l])
+            .collect();
+        let edited_chunks: Vec<&[u8]> = chunker
+            .chunks(&edited)
+            .into_iter()
+            .map(|(o, l)| &edited[o..o + l])
+            .collect();
+
+        // Fixed-size chunking would share zero chunks after a one-byte insertion; content-defined
+        // chunking should re-converge on most of the unaffected tail.
+        let shared = original_chunks
+            .iter()
+            .rev()
+            .zip(edited_chunks.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        assert!(shared > 0);
+    }
+}
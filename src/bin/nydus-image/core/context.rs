@@ -14,8 +14,12 @@ use std::io::{BufWriter, Cursor, Read, Seek, Write};
 use std::path::{Display, Path, PathBuf};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
 use anyhow::{Context, Error, Result};
+use crc32fast::Hasher as Crc32Hasher;
 use sha2::{Digest, Sha256};
 use tar::{EntryType, Header};
 use vmm_sys_util::tempfile::TempFile;
@@ -35,13 +39,15 @@ use nydus_storage::meta::{
     toc, BlobChunkInfoV2Ondisk, BlobMetaChunkArray, BlobMetaChunkInfo, BlobMetaHeaderOndisk,
     ZranContextGenerator,
 };
-use nydus_utils::digest::DigestData;
+use nydus_utils::digest::{DigestData, RafsDigest};
 use nydus_utils::{compress, digest, div_round_up, round_down_4k, BufReaderInfo};
 
 use super::chunk_dict::{ChunkDict, HashChunkDict};
+use super::chunker::{FastCdcChunker, FastCdcParams};
 use super::feature::Features;
 use super::node::{ChunkSource, Node, WhiteoutSpec};
 use super::prefetch::{Prefetch, PrefetchPolicy};
+use super::seek_table::SeekTableBuilder;
 
 // TODO: select BufWriter capacity by performance testing.
 pub const BUF_WRITER_CAPACITY: usize = 2 << 17;
@@ -122,6 +128,99 @@ impl ConversionType {
     }
 }
 
+/// Selects how a file's content is cut into chunks during the build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkingAlgorithm {
+    /// Cut every `chunk_size` bytes, regardless of content.
+    Fixed,
+    /// Content-defined chunking (FastCDC gear-hash variant), so boundaries follow content
+    /// instead of offset and survive small edits near the front of a file.
+    FastCdc,
+}
+
+impl Default for ChunkingAlgorithm {
+    fn default() -> Self {
+        Self::Fixed
+    }
+}
+
+impl FromStr for ChunkingAlgorithm {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "fixed" => Ok(Self::Fixed),
+            "fastcdc" => Ok(Self::FastCdc),
+            _ => Err(anyhow!("invalid chunking algorithm")),
+        }
+    }
+}
+
+/// Selects the at-rest encryption applied to a data blob's chunk payloads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cipher {
+    /// No encryption, chunk payloads are stored as produced by compression.
+    None,
+    /// AES-256-GCM, one independently authenticated ciphertext per chunk.
+    Aes256Gcm,
+}
+
+impl Default for Cipher {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+/// Length, in bytes, of the GCM authentication tag appended to each encrypted chunk.
+pub const AES_256_GCM_TAG_SIZE: usize = 16;
+
+/// Configuration for per-chunk adaptive compression: instead of a single algorithm for the whole
+/// blob, each chunk is compressed with every candidate in `candidates` and whichever yields the
+/// smallest output is kept (falling back to storing the chunk uncompressed when none of them
+/// help).
+///
+/// Not yet safe to enable: `BlobChunkInfoV2Ondisk`/`add_v2` (from `nydus_storage::meta`) have no
+/// field to record which algorithm a given chunk used, so a blob built with mixed algorithms can't
+/// be told apart on read. `BlobContext::compress_chunk` refuses to run while `enabled` is set,
+/// until that on-disk schema carries a per-chunk codec.
+#[derive(Debug, Clone)]
+pub struct AdaptiveCompression {
+    pub enabled: bool,
+    pub candidates: Vec<compress::Algorithm>,
+}
+
+impl Default for AdaptiveCompression {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            candidates: vec![
+                compress::Algorithm::Zstd,
+                compress::Algorithm::Lz4Block,
+                compress::Algorithm::None,
+            ],
+        }
+    }
+}
+
+/// Configuration for per-blob adaptive compression (`compress::Algorithm::Auto`): instead of
+/// picking an algorithm per chunk, a sample of the blob is compressed with every candidate once,
+/// and whichever gives the best size/throughput tradeoff is used for the whole blob. Cheaper than
+/// `AdaptiveCompression` (one decision instead of one per chunk) at the cost of not adapting to
+/// chunks whose content compresses very differently from the sample.
+#[derive(Debug, Clone)]
+pub struct BlobAdaptiveCompression {
+    pub enabled: bool,
+    pub candidates: Vec<compress::Algorithm>,
+}
+
+impl Default for BlobAdaptiveCompression {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            candidates: vec![compress::Algorithm::Zstd, compress::Algorithm::Lz4Block],
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ArtifactStorage {
     // Won't rename user's specification
@@ -230,12 +329,17 @@ pub struct ArtifactWriter {
     // Keep this because tmp file will be removed automatically when it is dropped.
     // But we will rename/link the tmp file before it is removed.
     tmp_file: Option<TempFile>,
+    // Rolling CRC32 over every byte written so far, surfaced through `crc32()` once the blob is
+    // complete. Kept regardless of whether `BlobFeatures::CRC32` is set, since updating it is
+    // cheap; callers decide whether to record it.
+    crc32: Crc32Hasher,
 }
 
 impl Write for ArtifactWriter {
     fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
         let n = self.file.write(bytes)?;
         self.pos += n;
+        self.crc32.update(&bytes[..n]);
         Ok(n)
     }
 
@@ -271,6 +375,7 @@ impl ArtifactWriter {
                     reader,
                     storage,
                     tmp_file: None,
+                    crc32: Crc32Hasher::new(),
                 })
             }
             ArtifactStorage::FileDir(ref p) => {
@@ -289,6 +394,7 @@ impl ArtifactWriter {
                     reader,
                     storage,
                     tmp_file: Some(tmp),
+                    crc32: Crc32Hasher::new(),
                 })
             }
         }
@@ -298,6 +404,11 @@ impl ArtifactWriter {
         Ok(self.pos as u64)
     }
 
+    /// Rolling CRC32 over all bytes written so far, for blobs with `BlobFeatures::CRC32` set.
+    pub fn crc32(&self) -> u32 {
+        self.crc32.clone().finalize()
+    }
+
     // The `inline-bootstrap` option merges the blob and bootstrap into one
     // file. We need some header to index the location of the blob and bootstrap,
     // write_tar_header uses tar header that arranges the data as follows:
@@ -358,9 +469,18 @@ impl ArtifactWriter {
 pub struct BlobContext {
     /// Blob id (user specified or sha256(blob)).
     pub blob_id: String,
+    /// Index of this blob within `BlobManager`, set by `BlobManager::get_or_create_current_blob`
+    /// before the blob is added. Mixed into `chunk_nonce` so two blobs encrypted with the same
+    /// `cipher_key` never reuse a nonce, since `chunk_count` alone restarts from 0 in every blob.
+    pub blob_index: u32,
     pub blob_hash: Sha256,
     pub blob_compressor: compress::Algorithm,
     pub blob_digester: digest::Algorithm,
+    /// At-rest encryption applied to each chunk's compressed payload, see `write_data`.
+    pub blob_cipher: Cipher,
+    /// AES-256-GCM key used when `blob_cipher` is `Cipher::Aes256Gcm`, sourced from
+    /// `ConfigV2`/backend configuration (raw key or key file).
+    pub cipher_key: Option<[u8; 32]>,
     pub blob_prefetch_size: u64,
     /// Whether to generate blob metadata information.
     pub blob_meta_info_enabled: bool,
@@ -370,6 +490,32 @@ pub struct BlobContext {
     pub blob_meta_header: BlobMetaHeaderOndisk,
     /// Blob chunk digest array.
     pub blob_chunk_digest: Vec<DigestData>,
+    /// Whether to compute and record CRC32 checksums, gated behind `BlobFeatures::CRC32`.
+    pub crc32_enabled: bool,
+    /// Per-chunk CRC32 checksums, recorded in the same order as `blob_chunk_digest`. A cheap
+    /// pre-verification gate: a mismatch here means the chunk is corrupt without needing to pay
+    /// for the cryptographic digest check.
+    pub blob_chunk_crc32: Vec<u32>,
+    /// Rolling CRC32 over the whole compressed blob, copied from `ArtifactWriter::crc32` by
+    /// `finalize_crc32` and surfaced through `BuildOutput::blob_crc32`. It is not written into the
+    /// blob's ToC entry or into the blob table.
+    pub rafs_blob_crc32: u32,
+
+    /// Merkle tree root over `blob_chunk_digest`, see `compute_merkle_root`. Zeroed until that's
+    /// called; for a blob with no chunks that call sets it to the digest of empty input.
+    pub merkle_root: DigestData,
+    /// Height of `merkle_root`'s tree: 0 for an empty or single-chunk blob, otherwise the number
+    /// of pairwise-hashing rounds needed to reduce `blob_chunk_digest` to one digest.
+    pub merkle_tree_height: u32,
+
+    /// Seek table for the seekable-ZSTD compression mode, accumulating one entry per chunk as
+    /// chunks are added via `add_chunk_meta_info`. `None` unless `enable_zstd_seekable` was
+    /// called, i.e. this isn't a seekable-ZSTD blob.
+    pub zstd_seek_table: Option<SeekTableBuilder>,
+    /// Size in bytes of the seek table appended to this blob (skippable frame + footer), set by
+    /// `finalize_zstd_seek_table`; `RafsV6BlobTable` needs this to locate the real end of the
+    /// compressed chunk data.
+    pub zstd_seek_table_size: u64,
 
     /// Final compressed blob file size.
     pub compressed_blob_size: u64,
@@ -417,14 +563,24 @@ impl BlobContext {
         };
         let mut blob_ctx = Self {
             blob_id,
+            blob_index: 0,
             blob_hash: Sha256::new(),
             blob_compressor: compressor,
             blob_digester: digester,
+            blob_cipher: Cipher::None,
+            cipher_key: None,
             blob_prefetch_size: 0,
             blob_meta_info_enabled: false,
             blob_meta_info,
             blob_meta_header: BlobMetaHeaderOndisk::default(),
             blob_chunk_digest: Vec::new(),
+            crc32_enabled: features.contains(BlobFeatures::CRC32),
+            blob_chunk_crc32: Vec::new(),
+            rafs_blob_crc32: 0,
+            merkle_root: [0u8; 32],
+            merkle_tree_height: 0,
+            zstd_seek_table: None,
+            zstd_seek_table_size: 0,
 
             compressed_blob_size: 0,
             uncompressed_blob_size: 0,
@@ -459,6 +615,9 @@ impl BlobContext {
         if features.contains(BlobFeatures::INLINED_CHUNK_DIGEST) {
             blob_ctx.blob_meta_header.set_inlined_chunk_digest(true);
         }
+        if features.contains(BlobFeatures::ENCRYPTED) {
+            blob_ctx.blob_meta_header.set_encrypted(true);
+        }
 
         blob_ctx
     }
@@ -577,6 +736,91 @@ impl BlobContext {
         self.blob_meta_info_enabled = enable;
     }
 
+    /// Turn this blob into a seekable-ZSTD blob: each chunk is already an independent ZSTD frame,
+    /// so this just starts accumulating a seek table over them, see `zstd_seek_table`.
+    pub fn enable_zstd_seekable(&mut self) {
+        self.zstd_seek_table = Some(SeekTableBuilder::new(false));
+    }
+
+    /// Serialize the accumulated seek table and append it to `blob_writer`, recording its size in
+    /// `zstd_seek_table_size`. No-op if `enable_zstd_seekable` was never called.
+    pub fn finalize_zstd_seek_table(&mut self, blob_writer: &mut ArtifactWriter) -> Result<()> {
+        if let Some(builder) = self.zstd_seek_table.take() {
+            let table = builder.finalize(self.uncompressed_blob_size)?;
+            self.zstd_seek_table_size = table.len() as u64;
+            blob_writer.write_all(&table)?;
+        }
+        Ok(())
+    }
+
+    /// Build the Merkle tree over `blob_chunk_digest` (leaves, in chunk index order) and record
+    /// its root and height in `merkle_root`/`merkle_tree_height`, so a reader holding only the
+    /// root can verify a single chunk belongs to this blob in O(log n) instead of needing every
+    /// chunk digest.
+    pub fn compute_merkle_root(&mut self) {
+        let (root, height) = Self::merkle_tree(&self.blob_chunk_digest, self.blob_digester);
+        self.merkle_root = root;
+        self.merkle_tree_height = height;
+    }
+
+    /// Reduce `leaves` to a single root by repeatedly hashing adjacent pairs with `algorithm`
+    /// until one digest remains, promoting an unpaired trailing node to the next level unchanged.
+    ///
+    /// An empty blob's root is the digest of empty input; a single-chunk blob's root is that
+    /// chunk's own digest (height 0 in both cases, since there's nothing to pair).
+    fn merkle_tree(leaves: &[DigestData], algorithm: digest::Algorithm) -> (DigestData, u32) {
+        if leaves.is_empty() {
+            return (RafsDigest::from_buf(&[], algorithm).data, 0);
+        }
+
+        let mut level = leaves.to_vec();
+        let mut height = 0;
+        while level.len() > 1 {
+            let mut next = Vec::with_capacity((level.len() + 1) / 2);
+            for pair in level.chunks(2) {
+                if pair.len() == 2 {
+                    let mut combined = Vec::with_capacity(pair[0].len() + pair[1].len());
+                    combined.extend_from_slice(&pair[0]);
+                    combined.extend_from_slice(&pair[1]);
+                    next.push(RafsDigest::from_buf(&combined, algorithm).data);
+                } else {
+                    next.push(pair[0]);
+                }
+            }
+            level = next;
+            height += 1;
+        }
+
+        (level[0], height)
+    }
+
+    /// Enable or disable the CRC32 pre-verification checksums written by `write_data`, see
+    /// `BlobFeatures::CRC32`.
+    pub fn set_crc32_enabled(&mut self, enable: bool) {
+        self.crc32_enabled = enable;
+    }
+
+    /// Snapshot the rolling CRC32 of everything written to `blob_writer` so far into
+    /// `rafs_blob_crc32`, once the blob is complete. No-op when CRC32 isn't enabled for this blob.
+    pub fn finalize_crc32(&mut self, blob_writer: &ArtifactWriter) {
+        if self.crc32_enabled {
+            self.rafs_blob_crc32 = blob_writer.crc32();
+        }
+    }
+
+    /// Run every finalization step that needs to happen once this blob's data is fully written to
+    /// `blob_writer`, before it's added to the blob table.
+    ///
+    /// Order matters: `finalize_crc32` snapshots the CRC32 first so it covers exactly the blob's
+    /// data, before `finalize_zstd_seek_table` appends the seek table as trailing metadata.
+    /// `compute_merkle_root` only needs the already-collected chunk digests, so it can run last.
+    pub fn finalize(&mut self, blob_writer: &mut ArtifactWriter) -> Result<()> {
+        self.finalize_crc32(blob_writer);
+        self.finalize_zstd_seek_table(blob_writer)?;
+        self.compute_merkle_root();
+        Ok(())
+    }
+
     pub fn add_chunk_meta_info(
         &mut self,
         chunk: &ChunkWrapper,
@@ -613,6 +857,10 @@ impl BlobContext {
             }
         }
 
+        if let Some(seek_table) = self.zstd_seek_table.as_mut() {
+            seek_table.add_frame(chunk.compressed_size(), chunk.uncompressed_size(), None);
+        }
+
         Ok(())
     }
 
@@ -640,10 +888,196 @@ impl BlobContext {
         }
     }
 
+    /// Enable AES-256-GCM encryption of chunk payloads written through `write_data`.
+    ///
+    /// Also records `BlobFeatures::ENCRYPTED` in `blob_meta_header`, so the on-disk blob carries a
+    /// record that it's encrypted instead of looking like an ordinary blob.
+    pub fn set_cipher(&mut self, cipher: Cipher, key: [u8; 32]) {
+        self.blob_cipher = cipher;
+        self.cipher_key = Some(key);
+        if cipher != Cipher::None {
+            self.blob_meta_header.set_encrypted(true);
+        }
+    }
+
+    /// Derive a 96-bit nonce from this blob's index and the chunk's index within it.
+    ///
+    /// Mixing in `blob_index` is required for nonce uniqueness, not just a nicety:
+    /// `alloc_chunk_index` only guarantees `chunk_index` is unique *within* a blob, and restarts
+    /// from 0 for every new blob, so chunk index alone would reuse the same nonce across every
+    /// blob that shares `cipher_key` — reusing a (key, nonce) pair under AES-GCM breaks both
+    /// confidentiality and authentication of every message that shares it.
+    fn chunk_nonce(&self, chunk_index: u32) -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        nonce[..4].copy_from_slice(&self.blob_index.to_le_bytes());
+        nonce[4..8].copy_from_slice(&chunk_index.to_le_bytes());
+        nonce
+    }
+
     /// Helper to write data to blob and update blob hash.
+    ///
+    /// When `blob_cipher` is `Cipher::Aes256Gcm`, `data` (the already-compressed chunk payload)
+    /// is encrypted into a new buffer with the 16-byte GCM tag appended, so the on-disk chunk size grows
+    /// by `AES_256_GCM_TAG_SIZE`. The blob hash and `rafs_blob_digest` are computed over the
+    /// ciphertext, so integrity checks downstream keep working without needing the key.
+    ///
+    /// When `crc32_enabled` is set, a CRC32 of exactly the bytes written to disk is appended to
+    /// `blob_chunk_crc32`, one entry per chunk in `add_chunk_meta_info` order, for a cheap
+    /// pre-verification gate ahead of the cryptographic digest check.
     pub fn write_data(&mut self, blob_writer: &mut ArtifactWriter, data: &[u8]) -> Result<()> {
-        blob_writer.write_all(data)?;
-        self.blob_hash.update(data);
+        match self.blob_cipher {
+            Cipher::None => {
+                blob_writer.write_all(data)?;
+                self.blob_hash.update(data);
+                if self.crc32_enabled {
+                    self.blob_chunk_crc32.push(crc32fast::hash(data));
+                }
+            }
+            Cipher::Aes256Gcm => {
+                let key = self
+                    .cipher_key
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("missing AES-256-GCM key for encrypted blob"))?;
+                let nonce = self.chunk_nonce(self.chunk_count);
+                let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+                let ciphertext = cipher
+                    .encrypt(Nonce::from_slice(&nonce), data)
+                    .map_err(|e| anyhow!("failed to encrypt chunk {}: {}", self.chunk_count, e))?;
+                blob_writer.write_all(&ciphertext)?;
+                self.blob_hash.update(&ciphertext);
+                if self.crc32_enabled {
+                    self.blob_chunk_crc32.push(crc32fast::hash(&ciphertext));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Compress `data` with every algorithm in `candidates` and return whichever produced the
+    /// smallest output, alongside the compressed bytes.
+    ///
+    /// `compress::Algorithm::None` is always a safe candidate to include: it "compresses" to the
+    /// original bytes, so already-compressed or incompressible chunks fall back to being stored
+    /// as-is instead of paying for compression that doesn't help.
+    pub fn compress_chunk_adaptive(
+        candidates: &[compress::Algorithm],
+        data: &[u8],
+    ) -> Result<(compress::Algorithm, Vec<u8>)> {
+        ensure!(
+            !candidates.is_empty(),
+            "adaptive compression requires at least one candidate algorithm"
+        );
+
+        let mut best: Option<(compress::Algorithm, Vec<u8>)> = None;
+        for &algorithm in candidates {
+            let compressed = match algorithm {
+                compress::Algorithm::None => data.to_vec(),
+                _ => compress::compress(data, algorithm)
+                    .map(|(buf, _)| buf.into_owned())
+                    .with_context(|| format!("failed to compress chunk with {:?}", algorithm))?,
+            };
+            if best
+                .as_ref()
+                .map_or(true, |(_, b)| compressed.len() < b.len())
+            {
+                best = Some((algorithm, compressed));
+            }
+        }
+
+        // Safe to unwrap: the loop always runs at least once since `candidates` is non-empty.
+        Ok(best.unwrap())
+    }
+
+    /// Compress one chunk's payload with this blob's configured `blob_compressor`.
+    ///
+    /// Does not call `compress_chunk_adaptive`, and returns an error while `ctx.adaptive_compression.enabled` is set, see
+    /// `AdaptiveCompression`'s doc comment: nothing downstream of this method can record which
+    /// algorithm a chunk ended up using, so letting it through would silently build an
+    /// undecodable blob instead of failing loudly.
+    pub fn compress_chunk(
+        &self,
+        ctx: &BuildContext,
+        data: &[u8],
+    ) -> Result<(compress::Algorithm, Vec<u8>)> {
+        ensure!(
+            !ctx.adaptive_compression.enabled,
+            "per-chunk adaptive compression is not yet safe to use: the chosen algorithm is never \
+             persisted per-chunk, so a blob built with mixed algorithms could not be decompressed \
+             correctly by any reader"
+        );
+
+        match self.blob_compressor {
+            compress::Algorithm::None => Ok((compress::Algorithm::None, data.to_vec())),
+            algorithm => compress::compress(data, algorithm)
+                .map(|(buf, _)| (algorithm, buf.into_owned()))
+                .with_context(|| format!("failed to compress chunk with {:?}", algorithm)),
+        }
+    }
+
+    /// Per-blob counterpart of `compress_chunk_adaptive`: sample `data` (typically the first
+    /// chunk or two) against every algorithm in `candidates` and set `blob_compressor` to
+    /// whichever strikes the best size/throughput tradeoff, so `to_blob_table` later emits the
+    /// matching `RafsSuperFlags` for the whole blob.
+    ///
+    /// Unlike the per-chunk variant, which always keeps the smallest output, this weighs
+    /// compression time too: a candidate only wins on a marginally smaller sample if it didn't
+    /// take disproportionately longer to produce it, since every chunk in the blob pays that cost
+    /// again at actual compression time.
+    pub fn select_blob_compressor_adaptive(
+        &mut self,
+        candidates: &[compress::Algorithm],
+        data: &[u8],
+    ) -> Result<()> {
+        ensure!(
+            !candidates.is_empty(),
+            "adaptive blob compression requires at least one candidate algorithm"
+        );
+
+        if data.is_empty() {
+            self.blob_compressor = candidates[0];
+            return Ok(());
+        }
+
+        let mut best: Option<(compress::Algorithm, u128)> = None;
+        for &algorithm in candidates {
+            let start = Instant::now();
+            let compressed_size = match algorithm {
+                compress::Algorithm::None => data.len(),
+                _ => compress::compress(data, algorithm)
+                    .map(|(buf, _)| buf.len())
+                    .with_context(|| {
+                        format!("failed to sample-compress blob with {:?}", algorithm)
+                    })?,
+            };
+            // Smaller is better on both axes, so the size*time product scores the tradeoff: a
+            // candidate that halves the size but takes twice as long scores the same as one that
+            // leaves size and time unchanged.
+            let score = compressed_size as u128 * start.elapsed().as_nanos().max(1);
+            if best.map_or(true, |(_, best_score)| score < best_score) {
+                best = Some((algorithm, score));
+            }
+        }
+
+        // Safe to unwrap: the loop always runs at least once since `candidates` is non-empty.
+        self.blob_compressor = best.unwrap().0;
+        Ok(())
+    }
+
+    /// Pick this blob's compressor from `sample`, honoring `ctx.blob_adaptive_compression`: when
+    /// enabled, delegate to `select_blob_compressor_adaptive`; otherwise leave `blob_compressor`
+    /// as already configured. Call once per blob, before compressing its chunks, with `sample`
+    /// being the first chunk or two of its content.
+    pub fn maybe_select_blob_compressor(
+        &mut self,
+        ctx: &BuildContext,
+        sample: &[u8],
+    ) -> Result<()> {
+        if ctx.blob_adaptive_compression.enabled {
+            self.select_blob_compressor_adaptive(
+                &ctx.blob_adaptive_compression.candidates,
+                sample,
+            )?;
+        }
         Ok(())
     }
 
@@ -698,6 +1132,9 @@ impl BlobManager {
         );
         blob_ctx.set_chunk_size(ctx.chunk_size);
         blob_ctx.set_meta_info_enabled(ctx.fs_version == RafsVersion::V6);
+        if let Some(key) = ctx.cipher_key {
+            blob_ctx.set_cipher(ctx.cipher, key);
+        }
 
         Ok(blob_ctx)
     }
@@ -707,8 +1144,10 @@ impl BlobManager {
         ctx: &BuildContext,
     ) -> Result<(u32, &mut BlobContext)> {
         if self.current_blob_index.is_none() {
-            let blob_ctx = Self::new_blob_ctx(ctx)?;
-            self.current_blob_index = Some(self.alloc_index()?);
+            let idx = self.alloc_index()?;
+            let mut blob_ctx = Self::new_blob_ctx(ctx)?;
+            blob_ctx.blob_index = idx;
+            self.current_blob_index = Some(idx);
             self.add(blob_ctx);
         }
         // Safe to unwrap because the blob context has been added.
@@ -769,6 +1208,15 @@ impl BlobManager {
         self.blobs.last()
     }
 
+    /// Run `BlobContext::finalize` for the current blob once all its data has been written to
+    /// `blob_writer`. No-op if no blob is current.
+    pub fn finalize_current_blob(&mut self, blob_writer: &mut ArtifactWriter) -> Result<()> {
+        if let Some((_, blob_ctx)) = self.get_current_blob() {
+            blob_ctx.finalize(blob_writer)?;
+        }
+        Ok(())
+    }
+
     pub fn get_blob_idx_by_id(&self, id: &str) -> Option<u32> {
         for (idx, blob) in self.blobs.iter().enumerate() {
             if blob.blob_id.eq(id) {
@@ -1002,6 +1450,19 @@ pub struct BuildContext {
     pub whiteout_spec: WhiteoutSpec,
     /// Chunk slice size.
     pub chunk_size: u32,
+    /// Algorithm used to cut file content into chunks.
+    pub chunking: ChunkingAlgorithm,
+    /// Size parameters for `ChunkingAlgorithm::FastCdc`.
+    pub fastcdc_params: FastCdcParams,
+    /// Per-chunk adaptive compression settings, see `BlobContext::compress_chunk_adaptive`.
+    pub adaptive_compression: AdaptiveCompression,
+    /// Per-blob adaptive compression settings (`compress::Algorithm::Auto`), see
+    /// `BlobContext::select_blob_compressor_adaptive`.
+    pub blob_adaptive_compression: BlobAdaptiveCompression,
+    /// At-rest encryption applied to each blob's chunk payloads, see `BlobContext::set_cipher`.
+    pub cipher: Cipher,
+    /// AES-256-GCM key used when `cipher` is `Cipher::Aes256Gcm`.
+    pub cipher_key: Option<[u8; 32]>,
     /// Version number of output metadata and data blob.
     pub fs_version: RafsVersion,
 
@@ -1059,6 +1520,12 @@ impl BuildContext {
             whiteout_spec,
 
             chunk_size: RAFS_DEFAULT_CHUNK_SIZE as u32,
+            chunking: ChunkingAlgorithm::default(),
+            fastcdc_params: FastCdcParams::default(),
+            adaptive_compression: AdaptiveCompression::default(),
+            blob_adaptive_compression: BlobAdaptiveCompression::default(),
+            cipher: Cipher::default(),
+            cipher_key: None,
             fs_version: RafsVersion::default(),
 
             conversion_type: source_type,
@@ -1085,9 +1552,43 @@ impl BuildContext {
         self.chunk_size = chunk_size;
     }
 
+    pub fn set_chunking(&mut self, chunking: ChunkingAlgorithm, params: FastCdcParams) {
+        self.chunking = chunking;
+        self.fastcdc_params = params;
+    }
+
+    /// Cut `data` into chunk boundaries according to `self.chunking`, returning each chunk as an
+    /// `(offset, len)` pair into `data`.
+    pub fn cut_chunks(&self, data: &[u8]) -> Vec<(usize, usize)> {
+        match self.chunking {
+            ChunkingAlgorithm::Fixed => {
+                let chunk_size = self.chunk_size as usize;
+                if chunk_size == 0 || data.is_empty() {
+                    return Vec::new();
+                }
+                let mut chunks =
+                    Vec::with_capacity(div_round_up(data.len() as u64, chunk_size as u64) as usize);
+                let mut offset = 0;
+                while offset < data.len() {
+                    let len = std::cmp::min(chunk_size, data.len() - offset);
+                    chunks.push((offset, len));
+                    offset += len;
+                }
+                chunks
+            }
+            ChunkingAlgorithm::FastCdc => FastCdcChunker::new(self.fastcdc_params).chunks(data),
+        }
+    }
+
     pub fn set_configuration(&mut self, config: Arc<ConfigV2>) {
         self.configuration = config;
     }
+
+    /// Enable AES-256-GCM encryption of every blob's chunk payloads, see `BlobContext::set_cipher`.
+    pub fn set_cipher(&mut self, cipher: Cipher, key: [u8; 32]) {
+        self.cipher = cipher;
+        self.cipher_key = Some(key);
+    }
 }
 
 impl Default for BuildContext {
@@ -1102,6 +1603,12 @@ impl Default for BuildContext {
             whiteout_spec: WhiteoutSpec::default(),
 
             chunk_size: RAFS_DEFAULT_CHUNK_SIZE as u32,
+            chunking: ChunkingAlgorithm::default(),
+            fastcdc_params: FastCdcParams::default(),
+            adaptive_compression: AdaptiveCompression::default(),
+            blob_adaptive_compression: BlobAdaptiveCompression::default(),
+            cipher: Cipher::default(),
+            cipher_key: None,
             fs_version: RafsVersion::default(),
 
             conversion_type: ConversionType::default(),
@@ -1129,6 +1636,28 @@ pub struct BuildOutput {
     pub blob_size: Option<u64>,
     /// File path for the metadata blob.
     pub bootstrap_path: Option<String>,
+    /// CRC32 of the last blob, set by `BlobContext::finalize_crc32`. `None` if CRC32 wasn't
+    /// enabled for that blob.
+    ///
+    /// Surfaced here for build-time visibility only: `RafsV5BlobTable`/`RafsV6BlobTable::add`
+    /// (from `nydus_rafs`, not present as source in this checkout) have no crc32 field, so this
+    /// value does not yet reach the on-disk blob table that ships with the image.
+    pub blob_crc32: Option<u32>,
+    /// Size in bytes of the seek table appended to the last blob, set by
+    /// `BlobContext::finalize_zstd_seek_table`. `None` (rather than `Some(0)`) when the blob isn't
+    /// seekable-ZSTD, i.e. `enable_zstd_seekable` was never called for it.
+    ///
+    /// Surfaced here for build-time visibility only: like `blob_crc32`, `RafsV5BlobTable`/
+    /// `RafsV6BlobTable::add` have no field for it, so it doesn't yet reach the on-disk blob table.
+    pub blob_zstd_seek_table_size: Option<u64>,
+    /// Merkle tree root and height over the last blob's chunk digests, set by
+    /// `BlobContext::compute_merkle_root`.
+    ///
+    /// Surfaced here for build-time visibility only, same caveat as `blob_crc32`:
+    /// `RafsV5BlobTable`/`RafsV6BlobTable::add` have no merkle fields, so neither value reaches
+    /// the on-disk blob table yet.
+    pub blob_merkle_root: Option<DigestData>,
+    pub blob_merkle_tree_height: Option<u32>,
 }
 
 impl fmt::Display for BuildOutput {
@@ -1143,6 +1672,20 @@ impl fmt::Display for BuildOutput {
             "data blob size: 0x{:x}",
             self.blob_size.unwrap_or_default()
         )?;
+        if let Some(crc32) = self.blob_crc32 {
+            writeln!(f, "data blob crc32: 0x{:x}", crc32)?;
+        }
+        if let Some(size) = self.blob_zstd_seek_table_size {
+            writeln!(f, "data blob zstd seek table size: 0x{:x}", size)?;
+        }
+        if let Some(root) = self.blob_merkle_root {
+            writeln!(f, "data blob merkle root: {}", hex::encode(root))?;
+            writeln!(
+                f,
+                "data blob merkle tree height: {}",
+                self.blob_merkle_tree_height.unwrap_or_default()
+            )?;
+        }
         write!(f, "data blobs: {:?}", self.blobs)?;
         Ok(())
     }
@@ -1155,6 +1698,22 @@ impl BuildOutput {
     ) -> Result<BuildOutput> {
         let blobs = blob_mgr.get_blob_ids();
         let blob_size = blob_mgr.get_last_blob().map(|b| b.compressed_blob_size);
+        let blob_crc32 = blob_mgr
+            .get_last_blob()
+            .filter(|b| b.crc32_enabled)
+            .map(|b| b.rafs_blob_crc32);
+        let blob_zstd_seek_table_size = blob_mgr
+            .get_last_blob()
+            .map(|b| b.zstd_seek_table_size)
+            .filter(|&size| size > 0);
+        let blob_merkle_root = blob_mgr
+            .get_last_blob()
+            .filter(|b| !b.blob_chunk_digest.is_empty())
+            .map(|b| b.merkle_root);
+        let blob_merkle_tree_height = blob_mgr
+            .get_last_blob()
+            .filter(|b| !b.blob_chunk_digest.is_empty())
+            .map(|b| b.merkle_tree_height);
         let bootstrap_path = if let Some(ArtifactStorage::SingleFile(p)) = bootstrap_storage {
             Some(p.display().to_string())
         } else {
@@ -1165,6 +1724,298 @@ impl BuildOutput {
             blobs,
             blob_size,
             bootstrap_path,
+            blob_crc32,
+            blob_zstd_seek_table_size,
+            blob_merkle_root,
+            blob_merkle_tree_height,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cut_chunks_fixed_splits_by_chunk_size() {
+        let mut ctx = BuildContext::default();
+        ctx.set_chunking(ChunkingAlgorithm::Fixed, FastCdcParams::default());
+        ctx.set_chunk_size(4);
+
+        let data = vec![0u8; 10];
+        let chunks = ctx.cut_chunks(&data);
+
+        assert_eq!(chunks, vec![(0, 4), (4, 4), (8, 2)]);
+    }
+
+    #[test]
+    fn test_cut_chunks_fastcdc_dispatches_to_fastcdcchunker() {
+        let mut ctx = BuildContext::default();
+        ctx.set_chunking(ChunkingAlgorithm::FastCdc, FastCdcParams::default());
+
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let chunks = ctx.cut_chunks(&data);
+
+        let total: usize = chunks.iter().map(|&(_, len)| len).sum();
+        assert_eq!(total, data.len());
+    }
+
+    #[test]
+    fn test_write_data_round_trips_aes_256_gcm_encryption() {
+        let tmp_dir = std::env::temp_dir();
+        let mut writer = ArtifactWriter::new(ArtifactStorage::FileDir(tmp_dir), false).unwrap();
+        let mut blob_ctx = BlobContext::new(
+            "test-blob".to_string(),
+            0,
+            BlobFeatures::empty(),
+            compress::Algorithm::None,
+            digest::Algorithm::Sha256,
+        );
+        let key = [7u8; 32];
+        blob_ctx.set_cipher(Cipher::Aes256Gcm, key);
+
+        let plaintext = b"hello nydus blob chunk payload";
+        blob_ctx.write_data(&mut writer, plaintext).unwrap();
+
+        writer.file.flush().unwrap();
+        writer.reader.seek(std::io::SeekFrom::Start(0)).unwrap();
+        let mut ciphertext = Vec::new();
+        writer.reader.read_to_end(&mut ciphertext).unwrap();
+
+        assert_eq!(ciphertext.len(), plaintext.len() + AES_256_GCM_TAG_SIZE);
+
+        let nonce = blob_ctx.chunk_nonce(0);
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+        let decrypted = cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn test_set_cipher_records_encrypted_feature() {
+        let mut blob_ctx = BlobContext::new(
+            "test-blob".to_string(),
+            0,
+            BlobFeatures::empty(),
+            compress::Algorithm::None,
+            digest::Algorithm::Sha256,
+        );
+        assert!(!blob_ctx.blob_meta_header.is_encrypted());
+
+        blob_ctx.set_cipher(Cipher::Aes256Gcm, [7u8; 32]);
+        assert!(blob_ctx.blob_meta_header.is_encrypted());
+    }
+
+    #[test]
+    fn test_chunk_nonce_differs_across_blobs_sharing_a_key() {
+        let mut blob_a = BlobContext::new(
+            "blob-a".to_string(),
+            0,
+            BlobFeatures::empty(),
+            compress::Algorithm::None,
+            digest::Algorithm::Sha256,
+        );
+        blob_a.blob_index = 0;
+        let mut blob_b = BlobContext::new(
+            "blob-b".to_string(),
+            0,
+            BlobFeatures::empty(),
+            compress::Algorithm::None,
+            digest::Algorithm::Sha256,
+        );
+        blob_b.blob_index = 1;
+
+        // The same chunk index in two different blobs must not reuse a nonce, since both blobs
+        // may be encrypted with the same `cipher_key`.
+        assert_ne!(blob_a.chunk_nonce(0), blob_b.chunk_nonce(0));
+    }
+
+    #[test]
+    fn test_get_or_create_current_blob_assigns_blob_index() {
+        let ctx = BuildContext::default();
+        let mut blob_mgr = BlobManager::new(digest::Algorithm::Sha256);
+        let (idx, blob_ctx) = blob_mgr.get_or_create_current_blob(&ctx).unwrap();
+        assert_eq!(blob_ctx.blob_index, idx);
+    }
+
+    #[test]
+    fn test_new_blob_ctx_wires_build_context_cipher_into_blob_cipher() {
+        let mut ctx = BuildContext::default();
+        let key = [9u8; 32];
+        ctx.set_cipher(Cipher::Aes256Gcm, key);
+
+        let blob_ctx = BlobManager::new_blob_ctx(&ctx).unwrap();
+
+        assert_eq!(blob_ctx.blob_cipher, Cipher::Aes256Gcm);
+        assert_eq!(blob_ctx.cipher_key, Some(key));
+    }
+
+    #[test]
+    fn test_compress_chunk_uses_blob_compressor_when_adaptive_compression_is_disabled() {
+        let data = vec![0u8; 256];
+
+        let mut ctx = BuildContext::default();
+        let blob_ctx = BlobContext::new(
+            "test-blob".to_string(),
+            0,
+            BlobFeatures::empty(),
+            compress::Algorithm::Zstd,
+            digest::Algorithm::Sha256,
+        );
+
+        ctx.adaptive_compression.enabled = false;
+        let (algorithm, _) = blob_ctx.compress_chunk(&ctx, &data).unwrap();
+        assert_eq!(algorithm, compress::Algorithm::Zstd);
+    }
+
+    #[test]
+    fn test_compress_chunk_rejects_adaptive_compression_until_schema_supports_it() {
+        let data = vec![0u8; 256];
+
+        let mut ctx = BuildContext::default();
+        let blob_ctx = BlobContext::new(
+            "test-blob".to_string(),
+            0,
+            BlobFeatures::empty(),
+            compress::Algorithm::Zstd,
+            digest::Algorithm::Sha256,
+        );
+
+        ctx.adaptive_compression.enabled = true;
+        ctx.adaptive_compression.candidates = vec![compress::Algorithm::None];
+        assert!(blob_ctx.compress_chunk(&ctx, &data).is_err());
+    }
+
+    #[test]
+    fn test_compress_chunk_adaptive_still_picks_the_smallest_candidate_directly() {
+        let data = vec![0u8; 256];
+        let candidates = vec![compress::Algorithm::None, compress::Algorithm::Zstd];
+
+        let (algorithm, compressed) =
+            BlobContext::compress_chunk_adaptive(&candidates, &data).unwrap();
+        assert_eq!(algorithm, compress::Algorithm::Zstd);
+        assert!(compressed.len() < data.len());
+    }
+
+    #[test]
+    fn test_build_output_display_includes_crc32_when_present() {
+        let output = BuildOutput {
+            blob_crc32: Some(0xdead_beef),
+            ..Default::default()
+        };
+        assert!(format!("{}", output).contains("data blob crc32: 0xdeadbeef"));
+
+        let output = BuildOutput::default();
+        assert!(!format!("{}", output).contains("crc32"));
+    }
+
+    #[test]
+    fn test_build_output_display_includes_zstd_seek_table_size_when_present() {
+        let output = BuildOutput {
+            blob_zstd_seek_table_size: Some(0x100),
+            ..Default::default()
+        };
+        assert!(format!("{}", output).contains("data blob zstd seek table size: 0x100"));
+
+        let output = BuildOutput::default();
+        assert!(!format!("{}", output).contains("seek table"));
+    }
+
+    #[test]
+    fn test_build_output_display_includes_merkle_root_when_present() {
+        let output = BuildOutput {
+            blob_merkle_root: Some([0xab; 32]),
+            blob_merkle_tree_height: Some(3),
+            ..Default::default()
+        };
+        let rendered = format!("{}", output);
+        assert!(rendered.contains(&format!(
+            "data blob merkle root: {}",
+            hex::encode([0xab; 32])
+        )));
+        assert!(rendered.contains("data blob merkle tree height: 3"));
+
+        let output = BuildOutput::default();
+        assert!(!format!("{}", output).contains("merkle"));
+    }
+
+    #[test]
+    fn test_finalize_current_blob_surfaces_crc32_through_build_output() {
+        let ctx = BuildContext::default();
+        let mut blob_mgr = BlobManager::new(digest::Algorithm::Sha256);
+        let (_, blob_ctx) = blob_mgr.get_or_create_current_blob(&ctx).unwrap();
+        blob_ctx.set_crc32_enabled(true);
+
+        let tmp_dir = std::env::temp_dir();
+        let mut writer = ArtifactWriter::new(ArtifactStorage::FileDir(tmp_dir), false).unwrap();
+        writer.write_all(b"some blob bytes").unwrap();
+        let expected_crc32 = writer.crc32();
+
+        blob_mgr.finalize_current_blob(&mut writer).unwrap();
+
+        let output = BuildOutput::new(&blob_mgr, &None).unwrap();
+        assert_eq!(output.blob_crc32, Some(expected_crc32));
+    }
+
+    #[test]
+    fn test_finalize_current_blob_surfaces_zstd_seek_table_size_through_build_output() {
+        let ctx = BuildContext::default();
+        let mut blob_mgr = BlobManager::new(digest::Algorithm::Sha256);
+        let (_, blob_ctx) = blob_mgr.get_or_create_current_blob(&ctx).unwrap();
+        blob_ctx.enable_zstd_seekable();
+
+        let tmp_dir = std::env::temp_dir();
+        let mut writer = ArtifactWriter::new(ArtifactStorage::FileDir(tmp_dir), false).unwrap();
+
+        blob_mgr.finalize_current_blob(&mut writer).unwrap();
+
+        let output = BuildOutput::new(&blob_mgr, &None).unwrap();
+        assert!(output.blob_zstd_seek_table_size.unwrap() > 0);
+    }
+
+    #[test]
+    fn test_maybe_select_blob_compressor_dispatches_on_blob_adaptive_compression_flag() {
+        let sample = vec![0u8; 256];
+        let mut ctx = BuildContext::default();
+        let mut blob_ctx = BlobContext::new(
+            "test-blob".to_string(),
+            0,
+            BlobFeatures::empty(),
+            compress::Algorithm::Zstd,
+            digest::Algorithm::Sha256,
+        );
+
+        ctx.blob_adaptive_compression.enabled = false;
+        blob_ctx
+            .maybe_select_blob_compressor(&ctx, &sample)
+            .unwrap();
+        assert_eq!(blob_ctx.blob_compressor, compress::Algorithm::Zstd);
+
+        ctx.blob_adaptive_compression.enabled = true;
+        ctx.blob_adaptive_compression.candidates = vec![compress::Algorithm::Lz4Block];
+        blob_ctx
+            .maybe_select_blob_compressor(&ctx, &sample)
+            .unwrap();
+        assert_eq!(blob_ctx.blob_compressor, compress::Algorithm::Lz4Block);
+    }
+
+    #[test]
+    fn test_finalize_surfaces_merkle_root_through_build_output() {
+        let ctx = BuildContext::default();
+        let mut blob_mgr = BlobManager::new(digest::Algorithm::Sha256);
+        let (_, blob_ctx) = blob_mgr.get_or_create_current_blob(&ctx).unwrap();
+        blob_ctx.blob_chunk_digest.push([1u8; 32]);
+        blob_ctx.blob_chunk_digest.push([2u8; 32]);
+
+        let tmp_dir = std::env::temp_dir();
+        let mut writer = ArtifactWriter::new(ArtifactStorage::FileDir(tmp_dir), false).unwrap();
+
+        blob_mgr.finalize_current_blob(&mut writer).unwrap();
+
+        let output = BuildOutput::new(&blob_mgr, &None).unwrap();
+        let blob_ctx = blob_mgr.get_last_blob().unwrap();
+        assert_eq!(output.blob_merkle_root, Some(blob_ctx.merkle_root));
+        assert_eq!(output.blob_merkle_tree_height, Some(1));
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright (C) 2023 Nydus Developers. All rights reserved.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Seek table for the seekable-ZSTD blob compression mode.
+//!
+//! Nydus already compresses each chunk as an independent ZSTD frame, so a blob compressed this
+//! way is already seekable at the chunk granularity. What's missing is a directory of
+//! `(compressed_size, decompressed_size)` per frame, so a reader doesn't have to decompress the
+//! whole blob up front to find where a given chunk's frame starts. This module builds that
+//! directory and serializes it as a ZSTD skippable frame appended after the compressed data, per
+//! the upstream seekable format
+//! (<https://github.com/facebook/zstd/blob/dev/contrib/seekable_format/zstd_seekable.h>), so
+//! tools that don't know about it still see an ordinary, ignorable skippable frame rather than
+//! garbage trailing the blob.
+//!
+//! `SeekTableBuilder` is driven from `BlobContext`: `enable_zstd_seekable` starts one,
+//! `add_chunk_meta_info` feeds it a frame per chunk, and `finalize_zstd_seek_table` (called from
+//! `BlobContext::finalize`) serializes and appends it.
+//!
+//! On disk, the table is a ZSTD skippable frame: a 4-byte magic, a 4-byte content length, then one
+//! `(compressed_size, decompressed_size[, checksum])` record per frame in stream order, followed by
+//! a footer holding the frame count, a descriptor byte (bit 7 set when checksums are present), and
+//! a closing magic.
+
+use anyhow::{ensure, Result};
+
+/// Magic number of the skippable frame wrapping the seek table, so ZSTD-aware tools that don't
+/// understand the seekable format skip over it instead of choking on it.
+pub const ZSTD_SKIPPABLE_FRAME_MAGIC: u32 = 0x184D2A5E;
+/// Magic number closing the seek table content, inside the skippable frame.
+pub const ZSTD_SEEKABLE_FOOTER_MAGIC: u32 = 0x8F92EAB1;
+
+/// One independently-decompressible ZSTD frame, usually corresponding to a single RAFS chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeekTableEntry {
+    pub compressed_size: u32,
+    pub decompressed_size: u32,
+    pub checksum: Option<u32>,
+}
+
+/// Accumulates one `SeekTableEntry` per frame as a blob is written, and serializes them into the
+/// skippable frame appended to the blob once it's complete.
+#[derive(Debug, Default)]
+pub struct SeekTableBuilder {
+    entries: Vec<SeekTableEntry>,
+    with_checksum: bool,
+}
+
+impl SeekTableBuilder {
+    /// Create a builder. When `with_checksum` is set, each recorded frame carries the low 32 bits
+    /// of the XXH64 of its decompressed content, for extra corruption detection at seek time.
+    pub fn new(with_checksum: bool) -> Self {
+        SeekTableBuilder {
+            entries: Vec::new(),
+            with_checksum,
+        }
+    }
+
+    /// Record one more frame, in stream order.
+    pub fn add_frame(
+        &mut self,
+        compressed_size: u32,
+        decompressed_size: u32,
+        checksum: Option<u32>,
+    ) {
+        self.entries.push(SeekTableEntry {
+            compressed_size,
+            decompressed_size,
+            checksum: if self.with_checksum { checksum } else { None },
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Serialize the accumulated entries into the skippable frame to append to the blob.
+    ///
+    /// `uncompressed_blob_size` must equal the sum of every recorded frame's `decompressed_size`;
+    /// this catches a short last frame (or a frame recorded against the wrong blob) before it
+    /// becomes a silently truncated seek table. An empty blob (no frames, size 0) is valid and
+    /// produces a well-formed table with zero entries.
+    pub fn finalize(&self, uncompressed_blob_size: u64) -> Result<Vec<u8>> {
+        let summed: u64 = self
+            .entries
+            .iter()
+            .map(|e| e.decompressed_size as u64)
+            .sum();
+        ensure!(
+            summed == uncompressed_blob_size,
+            "zstd seek table covers {} decompressed bytes, expected {}",
+            summed,
+            uncompressed_blob_size
+        );
+
+        let mut content = Vec::new();
+        for entry in &self.entries {
+            content.extend_from_slice(&entry.compressed_size.to_le_bytes());
+            content.extend_from_slice(&entry.decompressed_size.to_le_bytes());
+            if self.with_checksum {
+                content.extend_from_slice(&entry.checksum.unwrap_or(0).to_le_bytes());
+            }
+        }
+
+        // Seek_Table_Descriptor: bit 7 signals whether per-frame checksums are present.
+        let descriptor: u8 = if self.with_checksum { 0b1000_0000 } else { 0 };
+        content.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        content.push(descriptor);
+        content.extend_from_slice(&ZSTD_SEEKABLE_FOOTER_MAGIC.to_le_bytes());
+
+        let mut frame = Vec::with_capacity(content.len() + 8);
+        frame.extend_from_slice(&ZSTD_SKIPPABLE_FRAME_MAGIC.to_le_bytes());
+        frame.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&content);
+
+        Ok(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_blob_produces_a_well_formed_empty_table() {
+        let builder = SeekTableBuilder::new(false);
+        let table = builder.finalize(0).unwrap();
+        // magic(4) + frame_size(4) + footer (number_of_frames(4) + descriptor(1) + magic(4)).
+        assert_eq!(table.len(), 8 + 9);
+        assert_eq!(
+            u32::from_le_bytes(table[0..4].try_into().unwrap()),
+            ZSTD_SKIPPABLE_FRAME_MAGIC
+        );
+        assert_eq!(
+            u32::from_le_bytes(table[table.len() - 4..].try_into().unwrap()),
+            ZSTD_SEEKABLE_FOOTER_MAGIC
+        );
+    }
+
+    #[test]
+    fn test_mismatched_decompressed_total_is_rejected() {
+        let mut builder = SeekTableBuilder::new(false);
+        builder.add_frame(100, 1000, None);
+        assert!(builder.finalize(2000).is_err());
+    }
+
+    #[test]
+    fn test_round_trips_entry_count_and_descriptor() {
+        let mut builder = SeekTableBuilder::new(true);
+        builder.add_frame(100, 1000, Some(0xdead_beef));
+        builder.add_frame(50, 500, Some(0xcafe_babe));
+        let table = builder.finalize(1500).unwrap();
+
+        let footer = &table[table.len() - 9..];
+        let number_of_frames = u32::from_le_bytes(footer[0..4].try_into().unwrap());
+        let descriptor = footer[4];
+        assert_eq!(number_of_frames, 2);
+        assert_eq!(descriptor, 0b1000_0000);
+    }
+}
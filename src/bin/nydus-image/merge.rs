@@ -2,7 +2,7 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -17,6 +17,25 @@ use nydus_rafs::builder::{
 use nydus_rafs::metadata::{RafsInodeExt, RafsSuper, RafsVersion};
 use nydus_storage::device::{BlobFeatures, BlobInfo};
 
+/// One row of the blob remap report: `blob_id` sat at `original_index` in `bootstrap`'s own blob
+/// table, and ended up at `final_index` in the merged blob table.
+#[derive(Debug, Clone)]
+pub struct BlobRemapEntry {
+    pub bootstrap: PathBuf,
+    pub blob_id: String,
+    pub original_index: u32,
+    pub final_index: u32,
+}
+
+/// Result of `Merger::merge`: the merged bootstrap/blob output plus the full blob remap report,
+/// so callers that care which blob ended up where (e.g. to cross-check against their own records)
+/// don't have to re-derive it from logs.
+#[derive(Debug, Clone)]
+pub struct MergeOutput {
+    pub build: BuildOutput,
+    pub remap_report: Vec<BlobRemapEntry>,
+}
+
 /// Struct to generate the merged RAFS bootstrap for an image from per layer RAFS bootstraps.
 ///
 /// A container image contains one or more layers, a RAFS bootstrap is built for each layer.
@@ -53,9 +72,14 @@ impl Merger {
     /// # Arguments
     /// - sources: contains one or more per layer bootstraps in order of lower to higher.
     /// - chunk_dict: contain the chunk dictionary used to build per layer boostrap, or None.
+    /// - parent_bootstrap: an already-merged bootstrap (e.g. from `docker commit`-style container
+    ///   commit) to use as the base of the tree instead of `sources[0]`; `sources` are then
+    ///   applied on top of it with the usual OCI whiteout semantics, or None to merge `sources`
+    ///   alone as before.
     #[allow(clippy::too_many_arguments)]
     pub fn merge(
         ctx: &mut BuildContext,
+        parent_bootstrap: Option<PathBuf>,
         sources: Vec<PathBuf>,
         blob_digests: Option<Vec<String>>,
         blob_sizes: Option<Vec<u64>>,
@@ -64,7 +88,7 @@ impl Merger {
         target: ArtifactStorage,
         chunk_dict: Option<PathBuf>,
         config_v2: Arc<ConfigV2>,
-    ) -> Result<BuildOutput> {
+    ) -> Result<MergeOutput> {
         if sources.is_empty() {
             bail!("source bootstrap list is empty , at least one bootstrap is required");
         }
@@ -118,8 +142,75 @@ impl Merger {
         let mut chunk_size = None;
         let mut tree: Option<Tree> = None;
         let mut blob_mgr = BlobManager::new(ctx.digester);
+        // Maps a blob id to its index in `blob_mgr`, kept in sync as blobs are added, so dedup
+        // against already-registered blobs (parent's, chunk dict's, or an earlier layer's) is
+        // O(1) instead of rescanning `blob_mgr.get_blobs()` for every blob of every layer.
+        let mut blob_id_to_index: HashMap<String, u32> = HashMap::new();
+        let mut remap_report = Vec::new();
+
+        // Layer index 0 is reserved for nodes coming from the parent bootstrap (if any), so the
+        // per-layer nodes built from `sources` below never collide with it.
+        let layer_base: u16 = if parent_bootstrap.is_some() { 1 } else { 0 };
 
-        for (layer_idx, bootstrap_path) in sources.iter().enumerate() {
+        if let Some(parent_bootstrap_path) = &parent_bootstrap {
+            let (rs, _) =
+                RafsSuper::load_from_file(parent_bootstrap_path, config_v2.clone(), true, false)
+                    .context(format!("load parent bootstrap {:?}", parent_bootstrap_path))?;
+            config
+                .get_or_insert_with(|| rs.meta.get_config())
+                .check_compatibility(&rs.meta)?;
+            fs_version = RafsVersion::try_from(rs.meta.version)
+                .context("failed to get RAFS version number")?;
+            ctx.compressor = rs.meta.get_compressor();
+            ctx.digester = rs.meta.get_digester();
+            ctx.explicit_uidgid = rs.meta.explicit_uidgid();
+
+            // The parent's blobs are registered into an otherwise-empty blob manager, verbatim
+            // and in the parent's own order, so the blob indices `Tree::from_bootstrap` embeds
+            // into the parent's chunks below stay valid without a remapping pass. Unlike a
+            // per-layer bootstrap the parent isn't held to the "at most one new blob" rule below:
+            // it's the accumulated result of (potentially many) earlier layers, not a single one.
+            for blob in rs.superblock.get_blob_infos() {
+                let blob_ctx = BlobContext::from(ctx, &blob, ChunkSource::Parent)?;
+                if let Some(chunk_size) = chunk_size {
+                    ensure!(
+                        chunk_size == blob_ctx.chunk_size,
+                        "can not merge bootstraps with inconsistent chunk size, parent bootstrap {:?} with chunk size {:x}, expected {:x}",
+                        parent_bootstrap_path,
+                        blob_ctx.chunk_size,
+                        chunk_size,
+                    );
+                } else {
+                    chunk_size = Some(blob_ctx.chunk_size);
+                }
+                let original_index = blob.blob_index();
+                let blob_id = blob_ctx.blob_id.clone();
+                let final_index = blob_mgr.len() as u32;
+                blob_mgr.add_blob(blob_ctx);
+                blob_id_to_index.insert(blob_id.clone(), final_index);
+                remap_report.push(BlobRemapEntry {
+                    bootstrap: parent_bootstrap_path.clone(),
+                    blob_id,
+                    original_index,
+                    final_index,
+                });
+            }
+
+            let mut dict = HashChunkDict::new(rs.meta.get_digester());
+            tree = Some(Tree::from_bootstrap(&rs, &mut dict)?);
+        }
+
+        for (idx, bootstrap_path) in sources.iter().enumerate() {
+            let layer_idx: u16 = u16::try_from(idx)
+                .ok()
+                .and_then(|v| v.checked_add(layer_base))
+                .with_context(|| {
+                    format!(
+                        "too many layers {}, limited to {}",
+                        sources.len() + layer_base as usize,
+                        u16::MAX
+                    )
+                })?;
             let (rs, _) = RafsSuper::load_from_file(bootstrap_path, config_v2.clone(), true, false)
                 .context(format!("load bootstrap {:?}", bootstrap_path))?;
             config
@@ -163,39 +254,49 @@ impl Merger {
                         // runtime, should change it to the hash of whole tar blob.
                         blob_ctx.blob_id = BlobInfo::get_blob_id_from_meta_path(bootstrap_path)?;
                     }
-                    if let Some(digest) = Self::get_digest_from_list(&blob_digests, layer_idx)? {
+                    if let Some(digest) = Self::get_digest_from_list(&blob_digests, idx)? {
                         if blob.has_feature(BlobFeatures::SEPARATE) {
                             blob_ctx.blob_meta_digest = digest;
                         } else {
                             blob_ctx.blob_id = hex::encode(digest);
                         }
                     }
-                    if let Some(size) = Self::get_size_from_list(&blob_sizes, layer_idx)? {
+                    if let Some(size) = Self::get_size_from_list(&blob_sizes, idx)? {
                         if blob.has_feature(BlobFeatures::SEPARATE) {
                             blob_ctx.blob_meta_size = size;
                         } else {
                             blob_ctx.compressed_blob_size = size;
                         }
                     }
-                    if let Some(digest) = Self::get_digest_from_list(&blob_toc_digests, layer_idx)?
-                    {
+                    if let Some(digest) = Self::get_digest_from_list(&blob_toc_digests, idx)? {
                         blob_ctx.blob_toc_digest = digest;
                     }
-                    if let Some(size) = Self::get_size_from_list(&blob_toc_sizes, layer_idx)? {
+                    if let Some(size) = Self::get_size_from_list(&blob_toc_sizes, idx)? {
                         blob_ctx.blob_toc_size = size as u32;
                     }
                 }
 
-                let mut found = false;
-                for (idx, blob) in blob_mgr.get_blobs().iter().enumerate() {
-                    if blob.blob_id == blob_ctx.blob_id {
-                        blob_idx_map.push(idx as u32);
-                        found = true;
-                    }
-                }
-                if !found {
-                    blob_idx_map.push(blob_mgr.len() as u32);
+                let original_index = blob.blob_index();
+                if let Some(&final_index) = blob_id_to_index.get(&blob_ctx.blob_id) {
+                    blob_idx_map.push(final_index);
+                    remap_report.push(BlobRemapEntry {
+                        bootstrap: bootstrap_path.clone(),
+                        blob_id: blob_ctx.blob_id.clone(),
+                        original_index,
+                        final_index,
+                    });
+                } else {
+                    let final_index = blob_mgr.len() as u32;
+                    let blob_id = blob_ctx.blob_id.clone();
+                    blob_idx_map.push(final_index);
                     blob_mgr.add_blob(blob_ctx);
+                    blob_id_to_index.insert(blob_id.clone(), final_index);
+                    remap_report.push(BlobRemapEntry {
+                        bootstrap: bootstrap_path.clone(),
+                        blob_id,
+                        original_index,
+                        final_index,
+                    });
                 }
             }
 
@@ -217,12 +318,9 @@ impl Merger {
                             chunk.set_blob_index(blob_idx_map[origin_blob_index]);
                         }
                         // Set node's layer index to distinguish same inode number (from bootstrap)
-                        // between different layers.
-                        node.layer_idx = u16::try_from(layer_idx).context(format!(
-                            "too many layers {}, limited to {}",
-                            layer_idx,
-                            u16::MAX
-                        ))?;
+                        // between different layers (and from the parent bootstrap's own layer 0,
+                        // see `layer_base`).
+                        node.layer_idx = layer_idx;
                         node.overlay = Overlay::UpperAddition;
                         match node.whiteout_type(WhiteoutSpec::Oci) {
                             // Insert whiteouts at the head, so they will be handled first when
@@ -257,6 +355,18 @@ impl Merger {
         bootstrap
             .dump(ctx, &mut bootstrap_storage, &mut bootstrap_ctx, &blob_table)
             .context(format!("dump bootstrap to {:?}", target.display()))?;
-        BuildOutput::new(&blob_mgr, &bootstrap_storage)
+
+        for entry in &remap_report {
+            debug!(
+                "blob remap: {:?}[{}] {} -> blob table index {}",
+                entry.bootstrap, entry.original_index, entry.blob_id, entry.final_index
+            );
+        }
+
+        let build = BuildOutput::new(&blob_mgr, &bootstrap_storage)?;
+        Ok(MergeOutput {
+            build,
+            remap_report,
+        })
     }
 }
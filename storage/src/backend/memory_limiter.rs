@@ -0,0 +1,123 @@
+// Copyright (C) 2023 Nydus Developers. All rights reserved.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! A byte-budget memory limiter used to bound scratch buffer allocations for `readv()` and
+//! prefetch paths, so a burst of concurrent requests can't allocate arbitrarily much memory and
+//! OOM the process.
+
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Tracks outstanding bytes reserved against a configured budget.
+///
+/// Callers reserve bytes before allocating a scratch buffer and get back a [`MemoryLimiterGuard`]
+/// that releases the reservation on drop. To avoid deadlocking when the budget itself is smaller
+/// than a single request, any reservation no larger than `min_request` is always granted
+/// immediately, even if that pushes `available` negative for a while.
+pub struct MemoryLimiter {
+    state: Mutex<u64>,
+    condvar: Condvar,
+    capacity: u64,
+    min_request: u64,
+}
+
+impl MemoryLimiter {
+    /// Create a new `MemoryLimiter` with `capacity` bytes of budget.
+    ///
+    /// `min_request` is the smallest reservation size that must always be allowed through,
+    /// regardless of how much budget remains.
+    pub fn new(capacity: u64, min_request: u64) -> Arc<MemoryLimiter> {
+        Arc::new(MemoryLimiter {
+            state: Mutex::new(capacity),
+            condvar: Condvar::new(),
+            capacity,
+            min_request,
+        })
+    }
+
+    /// Reserve `size` bytes from the budget, blocking until enough bytes are available.
+    ///
+    /// Requests no larger than `min_request` are always granted without waiting, so a single
+    /// oversubscribed in-flight request can't deadlock every other caller.
+    pub fn reserve(self: &Arc<Self>, size: u64) -> MemoryLimiterGuard {
+        if size <= self.min_request {
+            let mut available = self.state.lock().unwrap();
+            *available = available.saturating_sub(size);
+            return MemoryLimiterGuard {
+                limiter: self.clone(),
+                size,
+            };
+        }
+
+        let mut available = self.state.lock().unwrap();
+        while *available < size {
+            available = self.condvar.wait(available).unwrap();
+        }
+        *available -= size;
+
+        MemoryLimiterGuard {
+            limiter: self.clone(),
+            size,
+        }
+    }
+
+    /// Release `size` bytes back to the budget and wake up any waiters.
+    fn release(&self, size: u64) {
+        let mut available = self.state.lock().unwrap();
+        *available = std::cmp::min(self.capacity, *available + size);
+        self.condvar.notify_all();
+    }
+
+    /// Total configured budget, in bytes.
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Bytes currently available to reserve.
+    pub fn available(&self) -> u64 {
+        *self.state.lock().unwrap()
+    }
+
+    /// Bytes currently reserved by outstanding guards.
+    pub fn reserved(&self) -> u64 {
+        self.capacity.saturating_sub(self.available())
+    }
+}
+
+/// RAII guard releasing a [`MemoryLimiter`] reservation when dropped.
+pub struct MemoryLimiterGuard {
+    limiter: Arc<MemoryLimiter>,
+    size: u64,
+}
+
+impl Drop for MemoryLimiterGuard {
+    fn drop(&mut self) {
+        self.limiter.release(self.size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_and_release() {
+        let limiter = MemoryLimiter::new(1024, 64);
+        assert_eq!(limiter.available(), 1024);
+
+        let guard = limiter.reserve(512);
+        assert_eq!(limiter.available(), 512);
+        assert_eq!(limiter.reserved(), 512);
+
+        drop(guard);
+        assert_eq!(limiter.available(), 1024);
+    }
+
+    #[test]
+    fn test_reserve_always_allows_minimum() {
+        let limiter = MemoryLimiter::new(32, 64);
+        // Even though the request exceeds total capacity, it is no larger than `min_request`,
+        // so it must not block.
+        let _guard = limiter.reserve(64);
+    }
+}
@@ -0,0 +1,352 @@
+// Copyright (C) 2023 Nydus Developers. All rights reserved.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! Multi-mirror proxy support: an ordered list of mirror endpoints, a background health-check
+//! loop that probes them, and failover selection for `BlobReader::read`.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use nydus_utils::metrics::BackendMetrics;
+
+use super::{BackendError, BackendResult, BlobReader, MirrorConfig, ProxyConfig};
+
+/// Abstracts the actual health-probe transport so the health-check loop can be unit tested
+/// without a real network client.
+pub trait MirrorPing: Send + Sync {
+    /// Probe `ping_url` and return whether the mirror should be considered healthy.
+    fn ping(&self, ping_url: &str) -> bool;
+}
+
+/// Tracked state for a single mirror endpoint.
+pub struct MirrorState {
+    pub config: MirrorConfig,
+    healthy: AtomicBool,
+    requests: AtomicU64,
+    failures: AtomicU64,
+}
+
+impl MirrorState {
+    fn new(config: MirrorConfig) -> Self {
+        MirrorState {
+            config,
+            healthy: AtomicBool::new(true),
+            requests: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether this mirror is currently considered healthy.
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    /// Number of requests routed to this mirror.
+    pub fn request_count(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// Number of requests routed to this mirror that failed.
+    pub fn failure_count(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+}
+
+/// Manages a list of mirror endpoints, health-checking them in the background and selecting a
+/// healthy one for each request, falling back to the origin when all mirrors are down (if
+/// `fallback` is enabled).
+pub struct MirrorsManager {
+    mirrors: Vec<Arc<MirrorState>>,
+    current: AtomicUsize,
+    fallback: bool,
+    check_interval: u64,
+}
+
+impl MirrorsManager {
+    /// Build a `MirrorsManager` from the mirror list in `config`.
+    pub fn new(config: &ProxyConfig) -> Arc<MirrorsManager> {
+        let mirrors = config
+            .mirrors()
+            .iter()
+            .cloned()
+            .map(MirrorState::new)
+            .map(Arc::new)
+            .collect();
+
+        Arc::new(MirrorsManager {
+            mirrors,
+            current: AtomicUsize::new(0),
+            fallback: config.fallback(),
+            check_interval: config.check_interval(),
+        })
+    }
+
+    /// Spawn the background health-check loop, probing every mirror every `check_interval`
+    /// seconds with `pinger`. The returned `JoinHandle` is detached by callers that don't need to
+    /// wait for it; it runs for the lifetime of the process.
+    pub fn start_health_check(
+        self: &Arc<Self>,
+        pinger: Arc<dyn MirrorPing>,
+    ) -> thread::JoinHandle<()> {
+        let manager = self.clone();
+        thread::spawn(move || loop {
+            for mirror in &manager.mirrors {
+                let healthy = pinger.ping(&mirror.config.ping_url);
+                mirror.healthy.store(healthy, Ordering::Relaxed);
+            }
+            thread::sleep(Duration::from_secs(manager.check_interval.max(1)));
+        })
+    }
+
+    /// Select the next healthy mirror, starting the search from the mirror last selected; after a
+    /// failure `mark_down` advances that starting point past it, so repeated failover attempts
+    /// don't hammer the same dead endpoint.
+    pub fn select(&self) -> Option<Arc<MirrorState>> {
+        self.select_index().map(|idx| self.mirrors[idx].clone())
+    }
+
+    /// Same selection as `select`, but returns the index into the mirror list instead of the
+    /// `MirrorState` itself, for callers (like `MirroredReader`) that keep a parallel per-mirror
+    /// resource list (e.g. one `BlobReader` per mirror) and need to know which slot was picked.
+    fn select_index(&self) -> Option<usize> {
+        if self.mirrors.is_empty() {
+            return None;
+        }
+
+        let start = self.current.load(Ordering::Relaxed);
+        for offset in 0..self.mirrors.len() {
+            let idx = (start + offset) % self.mirrors.len();
+            if self.mirrors[idx].is_healthy() {
+                self.current.store(idx, Ordering::Relaxed);
+                self.mirrors[idx].requests.fetch_add(1, Ordering::Relaxed);
+                return Some(idx);
+            }
+        }
+
+        None
+    }
+
+    /// Mark `mirror` as failed and advance selection so the next `select()` picks a different
+    /// mirror instead of retrying the one that just failed.
+    pub fn mark_down(&self, mirror: &Arc<MirrorState>) {
+        mirror.healthy.store(false, Ordering::Relaxed);
+        mirror.failures.fetch_add(1, Ordering::Relaxed);
+        let idx = self.current.load(Ordering::Relaxed);
+        self.current.store(idx + 1, Ordering::Relaxed);
+    }
+
+    /// Whether requests should fall back to the origin when every mirror is unhealthy.
+    pub fn fallback_to_origin(&self) -> bool {
+        self.fallback
+    }
+
+    /// Per-mirror `(url, healthy, request_count, failure_count)` for metrics reporting.
+    pub fn stats(&self) -> Vec<(String, bool, u64, u64)> {
+        self.mirrors
+            .iter()
+            .map(|m| {
+                (
+                    m.config.url.clone(),
+                    m.is_healthy(),
+                    m.request_count(),
+                    m.failure_count(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// A `BlobReader` that serves every request from whichever mirror `MirrorsManager` currently
+/// selects, and reports failures back to it through `failover()`.
+///
+/// This is the concrete caller `MirrorsManager`/`MirrorState` were built for: a real backend
+/// constructs one inner `BlobReader` per configured mirror plus one for the origin, and wraps
+/// them all in a `MirroredReader` instead of handing out a single fixed reader. The default
+/// retry loop in `BlobReader::read` already calls `failover()` between attempts; here that marks
+/// the mirror that just failed as down (via `MirrorsManager::mark_down`) so the next attempt is
+/// routed to a different, healthy mirror.
+pub struct MirroredReader {
+    manager: Arc<MirrorsManager>,
+    mirrors: Vec<Arc<dyn BlobReader>>,
+    origin: Arc<dyn BlobReader>,
+    /// Index into `mirrors` of the reader that served the most recent request, or `None` when
+    /// the last request fell back to `origin` because every mirror was down.
+    active: Mutex<Option<usize>>,
+}
+
+impl MirroredReader {
+    /// Wrap `mirrors` (in the same order as `manager`'s configured mirror list) and `origin`
+    /// (served once every mirror is marked down, if `manager` allows falling back).
+    pub fn new(
+        manager: Arc<MirrorsManager>,
+        mirrors: Vec<Arc<dyn BlobReader>>,
+        origin: Arc<dyn BlobReader>,
+    ) -> Self {
+        MirroredReader {
+            manager,
+            mirrors,
+            origin,
+            active: Mutex::new(None),
+        }
+    }
+
+    /// The reader to serve the next request from, recording which one was picked so a later
+    /// `failover()` call knows which mirror to mark down.
+    fn current(&self) -> Arc<dyn BlobReader> {
+        let idx = self.manager.select_index();
+        *self.active.lock().unwrap() = idx;
+        match idx {
+            Some(idx) => self.mirrors[idx].clone(),
+            None => self.origin.clone(),
+        }
+    }
+}
+
+impl BlobReader for MirroredReader {
+    fn blob_size(&self) -> BackendResult<u64> {
+        self.current().blob_size()
+    }
+
+    fn try_read(&self, buf: &mut [u8], offset: u64) -> BackendResult<usize> {
+        self.current().try_read(buf, offset)
+    }
+
+    fn prefetch_blob_data_range(&self, ra_offset: u64, ra_size: u64) -> BackendResult<()> {
+        self.current().prefetch_blob_data_range(ra_offset, ra_size)
+    }
+
+    fn blob_id(&self) -> &str {
+        self.origin.blob_id()
+    }
+
+    fn backend_kind(&self) -> &'static str {
+        "mirror"
+    }
+
+    // All mirrors and the origin share one set of metrics, attributed to `origin`, since
+    // per-mirror metrics would double-count a single logical request that failed over between
+    // mirrors.
+    fn metrics(&self) -> &BackendMetrics {
+        self.origin.metrics()
+    }
+
+    fn retry_limit(&self) -> u8 {
+        self.mirrors.len() as u8
+    }
+
+    fn failover(&self) {
+        if let Some(idx) = self.active.lock().unwrap().take() {
+            self.manager.mark_down(&self.manager.mirrors[idx]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct AlwaysDown;
+    impl MirrorPing for AlwaysDown {
+        fn ping(&self, _ping_url: &str) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_select_without_mirrors_returns_none() {
+        let config = ProxyConfig::default();
+        let manager = MirrorsManager::new(&config);
+        assert!(manager.select().is_none());
+    }
+
+    #[test]
+    fn test_failover_skips_down_mirror() {
+        let mut config = ProxyConfig::default();
+        config.set_mirrors(vec![
+            MirrorConfig {
+                url: "http://mirror-a".to_string(),
+                ping_url: "http://mirror-a/ping".to_string(),
+            },
+            MirrorConfig {
+                url: "http://mirror-b".to_string(),
+                ping_url: "http://mirror-b/ping".to_string(),
+            },
+        ]);
+        let manager = MirrorsManager::new(&config);
+
+        let first = manager.select().unwrap();
+        assert_eq!(first.config.url, "http://mirror-a");
+
+        manager.mark_down(&first);
+        let second = manager.select().unwrap();
+        assert_eq!(second.config.url, "http://mirror-b");
+    }
+
+    struct FakeReader {
+        metrics: Arc<BackendMetrics>,
+        fail: bool,
+    }
+
+    impl FakeReader {
+        fn new(fail: bool) -> Self {
+            FakeReader {
+                metrics: BackendMetrics::new("fake", "fake"),
+                fail,
+            }
+        }
+    }
+
+    impl BlobReader for FakeReader {
+        fn blob_size(&self) -> BackendResult<u64> {
+            Ok(0)
+        }
+
+        fn try_read(&self, buf: &mut [u8], _offset: u64) -> BackendResult<usize> {
+            if self.fail {
+                Err(BackendError::Unsupported("mirror down".to_string()))
+            } else {
+                buf.iter_mut().for_each(|b| *b = 7);
+                Ok(buf.len())
+            }
+        }
+
+        fn prefetch_blob_data_range(&self, _ra_offset: u64, _ra_size: u64) -> BackendResult<()> {
+            Ok(())
+        }
+
+        fn metrics(&self) -> &BackendMetrics {
+            &self.metrics
+        }
+    }
+
+    #[test]
+    fn test_mirrored_reader_fails_over_to_a_healthy_mirror() {
+        let mut config = ProxyConfig::default();
+        config.set_mirrors(vec![
+            MirrorConfig {
+                url: "http://mirror-a".to_string(),
+                ping_url: String::new(),
+            },
+            MirrorConfig {
+                url: "http://mirror-b".to_string(),
+                ping_url: String::new(),
+            },
+        ]);
+        let manager = MirrorsManager::new(&config);
+
+        let mirror_a: Arc<dyn BlobReader> = Arc::new(FakeReader::new(true));
+        let mirror_b: Arc<dyn BlobReader> = Arc::new(FakeReader::new(false));
+        let origin: Arc<dyn BlobReader> = Arc::new(FakeReader::new(false));
+        let reader = MirroredReader::new(manager, vec![mirror_a, mirror_b], origin);
+
+        let mut buf = [0u8; 4];
+        // `read()`'s default retry loop (see `BlobReader::read`) should mark mirror-a down via
+        // `failover()` after its failing `try_read` and retry against mirror-b, which succeeds.
+        let n = reader.read(&mut buf, 0).unwrap();
+        assert_eq!(n, 4);
+        assert_eq!(buf, [7, 7, 7, 7]);
+    }
+}
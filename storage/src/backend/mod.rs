@@ -11,14 +11,25 @@ use vm_memory::VolatileSlice;
 use crate::utils::copyv;
 use crate::StorageError;
 
+pub use self::memory_limiter::{MemoryLimiter, MemoryLimiterGuard};
+pub use self::readahead::ReadaheadBuffer;
+pub use self::stream::BlobSeekReader;
+
 #[cfg(feature = "backend-localfs")]
 pub mod localfs;
+pub mod memory_limiter;
+#[cfg(any(feature = "backend-oss", feature = "backend-registry"))]
+pub mod mirror;
+#[cfg(feature = "backend-opendal")]
+pub mod opendal;
 #[cfg(feature = "backend-oss")]
 pub mod oss;
 #[cfg(feature = "backend-registry")]
 pub mod registry;
+pub mod readahead;
 #[cfg(any(feature = "backend-oss", feature = "backend-registry"))]
 pub mod request;
+pub mod stream;
 
 /// Error codes related to storage backend operations.
 #[derive(Debug)]
@@ -36,12 +47,87 @@ pub enum BackendError {
     #[cfg(feature = "backend-oss")]
     /// Error from OSS storage backend.
     Oss(self::oss::OssError),
+    #[cfg(feature = "backend-opendal")]
+    /// Error from the OpenDAL storage backend.
+    Opendal(self::opendal::OpendalError),
+    /// An error annotated with the request context (blob/offset/len/backend/attempt) that
+    /// produced it, so operators can correlate a failure with the specific chunk and mirror
+    /// involved without losing the original, downcastable error source.
+    WithContext(Box<BackendError>, ErrorContext),
+}
+
+impl BackendError {
+    /// Wrap this error with request context, for attaching to `ERROR_HOLDER`.
+    pub fn with_context(self, context: ErrorContext) -> BackendError {
+        BackendError::WithContext(Box::new(self), context)
+    }
+}
+
+impl std::fmt::Display for BackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackendError::WithContext(err, ctx) => write!(f, "{} ({})", ctx, err_to_string(err)),
+            _ => write!(f, "{}", err_to_string(self)),
+        }
+    }
+}
+
+fn err_to_string(err: &BackendError) -> String {
+    format!("{:?}", err)
+}
+
+/// Context describing the request that produced a `BackendError`, used to correlate failures
+/// with the specific blob, offset and mirror/backend involved.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorContext {
+    /// Blob the failed request was reading from.
+    pub blob_id: String,
+    /// Offset of the failed request, in bytes.
+    pub offset: u64,
+    /// Length of the failed request, in bytes.
+    pub len: usize,
+    /// Kind of backend that served (or failed to serve) the request, e.g. "oss", "registry".
+    pub backend_kind: &'static str,
+    /// 1-based attempt number, accounting for retries.
+    pub attempt: u8,
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "backend={} blob={} offset={} len={} attempt={}",
+            self.backend_kind, self.blob_id, self.offset, self.len, self.attempt
+        )
+    }
 }
 
 /// Specialized `Result` for storage backends.
 pub type BackendResult<T> = std::result::Result<T, BackendError>;
 
+/// A single mirror endpoint, with its own health-check ping URL.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MirrorConfig {
+    /// Mirror endpoint to route requests to.
+    pub url: String,
+    /// Endpoint probed by the health-check loop to decide if this mirror is up.
+    pub ping_url: String,
+}
+
+impl Default for MirrorConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            ping_url: String::new(),
+        }
+    }
+}
+
 /// Configuration information for network proxy.
+///
+/// `url`/`ping_url` are kept for backward compatibility and, when `mirrors` is empty, are treated
+/// as a single implicit mirror.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct ProxyConfig {
@@ -49,6 +135,8 @@ pub struct ProxyConfig {
     ping_url: String,
     fallback: bool,
     check_interval: u64,
+    /// Ordered list of mirror endpoints to fail over between.
+    mirrors: Vec<MirrorConfig>,
 }
 
 impl Default for ProxyConfig {
@@ -58,10 +146,43 @@ impl Default for ProxyConfig {
             ping_url: String::new(),
             fallback: true,
             check_interval: 5,
+            mirrors: Vec::new(),
         }
     }
 }
 
+impl ProxyConfig {
+    /// Get the configured mirror list, falling back to the single legacy `url`/`ping_url` pair
+    /// when `mirrors` wasn't set.
+    pub fn mirrors(&self) -> Vec<MirrorConfig> {
+        if !self.mirrors.is_empty() {
+            self.mirrors.clone()
+        } else if !self.url.is_empty() {
+            vec![MirrorConfig {
+                url: self.url.clone(),
+                ping_url: self.ping_url.clone(),
+            }]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Whether to fall back to the origin when every mirror is unhealthy.
+    pub fn fallback(&self) -> bool {
+        self.fallback
+    }
+
+    /// Health-check probe interval, in seconds.
+    pub fn check_interval(&self) -> u64 {
+        self.check_interval
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_mirrors(&mut self, mirrors: Vec<MirrorConfig>) {
+        self.mirrors = mirrors;
+    }
+}
+
 /// Generic configuration for storage backends.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(default)]
@@ -70,6 +191,15 @@ pub struct CommonConfig {
     timeout: u64,
     connect_timeout: u64,
     retry_limit: u8,
+    /// Maximum number of ranges to coalesce into a single multi-range request.
+    max_batch_ranges: usize,
+    /// Maximum total bytes to fetch with a single multi-range request.
+    max_batch_size: u64,
+    /// Maximum total bytes outstanding in `readv`/prefetch scratch buffers at once.
+    max_inflight_bytes: u64,
+    /// Clamp applied to `prefetch_blob_data_range`'s `ra_size`, so a single hint can't warm an
+    /// unbounded read-ahead buffer.
+    max_readahead: u64,
 }
 
 impl Default for CommonConfig {
@@ -79,6 +209,10 @@ impl Default for CommonConfig {
             timeout: 5,
             connect_timeout: 5,
             retry_limit: 0,
+            max_batch_ranges: 64,
+            max_batch_size: 4 * 1024 * 1024,
+            max_inflight_bytes: 128 * 1024 * 1024,
+            max_readahead: 16 * 1024 * 1024,
         }
     }
 }
@@ -106,26 +240,36 @@ pub trait BlobReader: Send + Sync {
     fn read(&self, buf: &mut [u8], offset: u64) -> BackendResult<usize> {
         let mut retry_count = self.retry_limit();
         let begin_time = self.metrics().begin();
+        let mut attempt: u8 = 0;
 
         loop {
+            attempt = attempt.saturating_add(1);
             match self.try_read(buf, offset) {
                 Ok(size) => {
                     self.metrics().end(&begin_time, buf.len(), false);
                     return Ok(size);
                 }
                 Err(err) => {
+                    let context = ErrorContext {
+                        blob_id: self.blob_id().to_string(),
+                        offset,
+                        len: buf.len(),
+                        backend_kind: self.backend_kind(),
+                        attempt,
+                    };
                     if retry_count > 0 {
-                        warn!(
-                            "Read from backend failed: {:?}, retry count {}",
-                            err, retry_count
-                        );
+                        warn!("Read from backend failed: {} ({:?}), retrying", context, err);
                         retry_count -= 1;
+                        // Give mirror-backed readers a chance to fail over to a different
+                        // healthy mirror instead of retrying the one that just failed.
+                        self.failover();
                     } else {
                         self.metrics().end(&begin_time, buf.len(), true);
+                        let err = err.with_context(context);
                         ERROR_HOLDER
                             .lock()
                             .unwrap()
-                            .push(&format!("{:?}", err))
+                            .push(&format!("{}", err))
                             .unwrap_or_else(|_| error!("Failed when try to hold error"));
                         return Err(err);
                     }
@@ -134,6 +278,19 @@ pub trait BlobReader: Send + Sync {
         }
     }
 
+    /// Identifier of the blob this reader serves, used to annotate `BackendError` context.
+    ///
+    /// The default implementation returns an empty string; concrete readers should override it.
+    fn blob_id(&self) -> &str {
+        ""
+    }
+
+    /// Short name of the backend kind (e.g. "oss", "registry", "opendal"), used to annotate
+    /// `BackendError` context.
+    fn backend_kind(&self) -> &'static str {
+        "unknown"
+    }
+
     /// Read a range of data from the blob file into the provided buffers.
     ///
     /// Read data of range [offset, offset + max_size) from the blob file, and returns:
@@ -150,18 +307,56 @@ pub trait BlobReader: Send + Sync {
             // Use std::alloc to avoid zeroing the allocated buffer.
             let size = bufs.iter().fold(0usize, move |size, s| size + s.len());
             let size = std::cmp::min(size, max_size);
+            // Bound the scratch allocation against the shared memory budget so a burst of
+            // concurrent `readv`/prefetch calls can't allocate arbitrarily much memory.
+            let _guard = self.memory_limiter().reserve(size as u64);
             let mut data = Vec::with_capacity(size);
             unsafe { data.set_len(size) };
 
-            self.read(blob_id, data, offset)?;
-            copyv(&[&data], bufs, offset, result, 0, 0)
+            self.read(&mut data, offset)?;
+            copyv(&[&data], bufs, offset as usize, max_size, 0, 0)
                 .map(|r| r.0)
                 .map_err(BackendError::CopyData)
         }
     }
 
+    /// Get the shared `MemoryLimiter` bounding scratch buffer allocations for `readv` and
+    /// prefetch paths.
+    ///
+    /// The default implementation imposes no limit; backends constructed with a configured
+    /// `CommonConfig::max_inflight_bytes` should override this to return their shared limiter.
+    fn memory_limiter(&self) -> Arc<MemoryLimiter> {
+        MemoryLimiter::new(u64::MAX, u64::MAX)
+    }
+
+    /// Read a batch of independent ranges from the blob file in as few backend requests as
+    /// possible.
+    ///
+    /// Each entry in `ranges` is a `(offset, len)` pair. The default implementation simply loops
+    /// over `try_read`; backends that support server-side multi-range requests (e.g. HTTP
+    /// `Range: bytes=a-b,c-d`) should override this to coalesce nearby ranges into a single round
+    /// trip, while still honoring `CommonConfig::max_batch_ranges`/`max_batch_size` so a single
+    /// batch can't grow without bound.
+    fn try_read_batch(&self, ranges: &[(u64, usize)]) -> BackendResult<Vec<Vec<u8>>> {
+        ranges
+            .iter()
+            .map(|&(offset, len)| {
+                let mut buf = vec![0u8; len];
+                let size = self.try_read(&mut buf, offset)?;
+                buf.truncate(size);
+                Ok(buf)
+            })
+            .collect()
+    }
+
     /// Give hints to prefetch blob data range.
-    fn prefetch_blob_data_range(&self, ra_offset: u32, ra_size: u32) -> BackendResult<()>;
+    ///
+    /// `ra_offset`/`ra_size` are 64-bit to support read-ahead hints into multi-gigabyte blobs.
+    /// Backends that support it should turn this into an actual range fetch that warms a local
+    /// read-ahead buffer (see [`crate::backend::readahead::ReadaheadBuffer`]) so that subsequent
+    /// `try_read` calls within `[ra_offset, ra_offset + ra_size)` can be served without another
+    /// backend round trip.
+    fn prefetch_blob_data_range(&self, ra_offset: u64, ra_size: u64) -> BackendResult<()>;
 
     /// Get metrics object.
     fn metrics(&self) -> &BackendMetrics;
@@ -170,6 +365,13 @@ pub trait BlobReader: Send + Sync {
     fn retry_limit(&self) -> u8 {
         0
     }
+
+    /// Called between retry attempts in `read()` so mirror-backed readers can mark the mirror
+    /// that just failed as down and fail over to another healthy one.
+    ///
+    /// The default implementation is a no-op; only backends that own a `MirrorsManager` need to
+    /// override it.
+    fn failover(&self) {}
 }
 
 pub trait BlobWrite: Send + Sync {
@@ -244,8 +446,8 @@ pub trait BlobBackend: Send + Sync {
     fn prefetch_blob_data_range(
         &self,
         blob_id: &str,
-        ra_offset: u32,
-        ra_size: u32,
+        ra_offset: u64,
+        ra_size: u64,
     ) -> BackendResult<()>;
 
     /// Write data from buffer into the blob file.
@@ -260,6 +462,35 @@ fn default_http_scheme() -> String {
     "https".to_string()
 }
 
+/// Group a list of `(offset, len)` ranges into sub-batches that each respect `max_ranges` entries
+/// and `max_size` total bytes, so a backend's multi-range request stays bounded regardless of how
+/// many ranges the caller asked for in one go.
+pub fn split_batches(
+    ranges: &[(u64, usize)],
+    max_ranges: usize,
+    max_size: u64,
+) -> Vec<Vec<(u64, usize)>> {
+    let mut batches = Vec::new();
+    let mut current: Vec<(u64, usize)> = Vec::new();
+    let mut current_size: u64 = 0;
+
+    for &(offset, len) in ranges {
+        let would_exceed_count = current.len() >= max_ranges.max(1);
+        let would_exceed_size = current_size + len as u64 > max_size.max(len as u64);
+        if !current.is_empty() && (would_exceed_count || would_exceed_size) {
+            batches.push(std::mem::take(&mut current));
+            current_size = 0;
+        }
+        current_size += len as u64;
+        current.push((offset, len));
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+
+    batches
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -281,5 +512,53 @@ mod tests {
         assert_eq!(config.proxy.fallback, true);
         assert_eq!(config.proxy.ping_url, "");
         assert_eq!(config.proxy.url, "");
+        assert_eq!(config.max_batch_ranges, 64);
+        assert_eq!(config.max_batch_size, 4 * 1024 * 1024);
+        assert_eq!(config.max_inflight_bytes, 128 * 1024 * 1024);
+        assert_eq!(config.max_readahead, 16 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_proxy_config_mirrors_fallback_to_legacy_url() {
+        let mut config = ProxyConfig::default();
+        assert!(config.mirrors().is_empty());
+
+        config.url = "http://proxy".to_string();
+        config.ping_url = "http://proxy/ping".to_string();
+        let mirrors = config.mirrors();
+        assert_eq!(mirrors.len(), 1);
+        assert_eq!(mirrors[0].url, "http://proxy");
+    }
+
+    #[test]
+    fn test_error_context_display() {
+        let context = ErrorContext {
+            blob_id: "blob-1".to_string(),
+            offset: 100,
+            len: 200,
+            backend_kind: "oss",
+            attempt: 2,
+        };
+        let err = BackendError::Unsupported("boom".to_string()).with_context(context);
+        let msg = format!("{}", err);
+        assert!(msg.contains("blob-1"));
+        assert!(msg.contains("oss"));
+        assert!(msg.contains("attempt=2"));
+    }
+
+    #[test]
+    fn test_split_batches() {
+        let ranges = vec![(0u64, 100usize), (100, 100), (200, 100), (300, 100)];
+
+        let batches = split_batches(&ranges, 2, 1024);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].len(), 2);
+        assert_eq!(batches[1].len(), 2);
+
+        let batches = split_batches(&ranges, 64, 150);
+        assert_eq!(batches.len(), 4);
+
+        let batches = split_batches(&[], 64, 1024);
+        assert!(batches.is_empty());
     }
 }
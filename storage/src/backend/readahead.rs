@@ -0,0 +1,166 @@
+// Copyright (C) 2023 Nydus Developers. All rights reserved.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! A small read-ahead buffer backing `BlobReader::prefetch_blob_data_range`, so a prefetch hint
+//! actually warms data that subsequent `try_read` calls within the hinted window can be served
+//! from, instead of being a no-op.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use super::{BackendResult, BlobReader};
+
+struct ReadaheadWindow {
+    offset: u64,
+    data: Vec<u8>,
+}
+
+/// Holds the most recently prefetched window of blob data.
+pub struct ReadaheadBuffer {
+    window: Mutex<Option<ReadaheadWindow>>,
+    prefetched_bytes: AtomicU64,
+}
+
+impl Default for ReadaheadBuffer {
+    fn default() -> Self {
+        ReadaheadBuffer {
+            window: Mutex::new(None),
+            prefetched_bytes: AtomicU64::new(0),
+        }
+    }
+}
+
+impl ReadaheadBuffer {
+    /// Create a new, empty `ReadaheadBuffer`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fetch `[offset, offset + size)` from `reader` and store it as the current window.
+    ///
+    /// The scratch buffer is reserved against `reader.memory_limiter()` before allocating, so a
+    /// burst of concurrent prefetch hints is bounded by the same budget as `readv()` instead of
+    /// allocating unconditionally.
+    pub fn warm(&self, reader: &dyn BlobReader, offset: u64, size: u64) -> BackendResult<()> {
+        if size == 0 {
+            return Ok(());
+        }
+
+        let _guard = reader.memory_limiter().reserve(size);
+        let mut data = vec![0u8; size as usize];
+        let read = reader.try_read(&mut data, offset)?;
+        data.truncate(read);
+        self.prefetched_bytes
+            .fetch_add(read as u64, Ordering::Relaxed);
+        *self.window.lock().unwrap() = Some(ReadaheadWindow { offset, data });
+
+        Ok(())
+    }
+
+    /// Try to serve `buf` starting at `offset` from the current window.
+    ///
+    /// Returns `None` when `offset` falls outside the buffered window, so the caller should fall
+    /// back to an actual backend read.
+    pub fn try_serve(&self, buf: &mut [u8], offset: u64) -> Option<usize> {
+        let window = self.window.lock().unwrap();
+        let window = window.as_ref()?;
+        if offset < window.offset || offset >= window.offset + window.data.len() as u64 {
+            return None;
+        }
+
+        let start = (offset - window.offset) as usize;
+        let available = &window.data[start..];
+        let n = std::cmp::min(available.len(), buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+
+        Some(n)
+    }
+
+    /// Total bytes ever pulled into this buffer by `warm()`, for metrics reporting.
+    pub fn prefetched_bytes(&self) -> u64 {
+        self.prefetched_bytes.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MemoryLimiter;
+    use nydus_utils::metrics::BackendMetrics;
+    use std::sync::Arc;
+
+    struct FakeReader(Vec<u8>, Arc<MemoryLimiter>);
+
+    impl FakeReader {
+        fn new(data: Vec<u8>) -> Self {
+            FakeReader(data, MemoryLimiter::new(u64::MAX, u64::MAX))
+        }
+    }
+
+    impl BlobReader for FakeReader {
+        fn blob_size(&self) -> BackendResult<u64> {
+            Ok(self.0.len() as u64)
+        }
+
+        fn try_read(&self, buf: &mut [u8], offset: u64) -> BackendResult<usize> {
+            let offset = offset as usize;
+            if offset >= self.0.len() {
+                return Ok(0);
+            }
+            let n = std::cmp::min(buf.len(), self.0.len() - offset);
+            buf[..n].copy_from_slice(&self.0[offset..offset + n]);
+            Ok(n)
+        }
+
+        fn prefetch_blob_data_range(&self, _ra_offset: u64, _ra_size: u64) -> BackendResult<()> {
+            Ok(())
+        }
+
+        fn metrics(&self) -> &BackendMetrics {
+            unimplemented!()
+        }
+
+        fn memory_limiter(&self) -> Arc<MemoryLimiter> {
+            self.1.clone()
+        }
+    }
+
+    #[test]
+    fn test_warm_and_serve() {
+        let reader = FakeReader::new((0u8..100).collect());
+        let buffer = ReadaheadBuffer::new();
+
+        buffer.warm(&reader, 10, 20).unwrap();
+        assert_eq!(buffer.prefetched_bytes(), 20);
+
+        let mut out = [0u8; 5];
+        let n = buffer.try_serve(&mut out, 15).unwrap();
+        assert_eq!(n, 5);
+        assert_eq!(out, [15, 16, 17, 18, 19]);
+
+        assert!(buffer.try_serve(&mut out, 5).is_none());
+        assert!(buffer.try_serve(&mut out, 31).is_none());
+    }
+
+    #[test]
+    fn test_warm_zero_size_is_noop() {
+        let reader = FakeReader::new(vec![1, 2, 3]);
+        let buffer = ReadaheadBuffer::new();
+        buffer.warm(&reader, 0, 0).unwrap();
+        let mut out = [0u8; 1];
+        assert!(buffer.try_serve(&mut out, 0).is_none());
+    }
+
+    #[test]
+    fn test_warm_reserves_against_the_reader_memory_limiter() {
+        let limiter = MemoryLimiter::new(1024, 64);
+        let reader = FakeReader(vec![0u8; 100], limiter.clone());
+        let buffer = ReadaheadBuffer::new();
+
+        buffer.warm(&reader, 0, 20).unwrap();
+
+        // The reservation is released once `warm` returns, so the budget should be back to full.
+        assert_eq!(limiter.available(), 1024);
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright (C) 2023 Nydus Developers. All rights reserved.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+//! A `std::io::{Read, Seek}` adapter over a `BlobReader`, for consumers (e.g. a streaming
+//! decompressor) that want to read a blob sequentially instead of issuing manual offset reads.
+
+use std::cmp::min;
+use std::io::{Read, Result as IoResult, Seek, SeekFrom};
+use std::sync::Arc;
+
+use super::BlobReader;
+
+/// Size of the internal read-ahead window used to serve sequential reads.
+const WINDOW_SIZE: usize = 1024 * 1024;
+
+/// A seekable, sequential reader over a `BlobReader`.
+///
+/// Reads are served from an internal buffer filled in `WINDOW_SIZE` windows. A seek whose target
+/// still falls within the currently buffered range is free, served out of the existing buffer
+/// with no backend request. Any other seek — backward, or forward past the end of the buffered
+/// range — drops the buffer so the next read re-fills it starting at the new position; there's no
+/// data to retain in that case since the buffered window and the new position don't overlap.
+pub struct BlobSeekReader {
+    reader: Arc<dyn BlobReader>,
+    blob_size: u64,
+    pos: u64,
+    buf: Vec<u8>,
+    buf_start: u64,
+}
+
+impl BlobSeekReader {
+    /// Create a new `BlobSeekReader` wrapping `reader`.
+    pub fn new(reader: Arc<dyn BlobReader>) -> IoResult<BlobSeekReader> {
+        let blob_size = reader
+            .blob_size()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{:?}", e)))?;
+        Ok(BlobSeekReader {
+            reader,
+            blob_size,
+            pos: 0,
+            buf: Vec::new(),
+            buf_start: 0,
+        })
+    }
+
+    fn buf_end(&self) -> u64 {
+        self.buf_start + self.buf.len() as u64
+    }
+
+    fn fill_buffer(&mut self) -> IoResult<()> {
+        if self.pos < self.buf_end() {
+            // Still within the already buffered window, nothing to do.
+            return Ok(());
+        }
+
+        let remaining = self.blob_size.saturating_sub(self.pos);
+        let want = min(remaining, WINDOW_SIZE as u64) as usize;
+        let mut buf = vec![0u8; want];
+        if want > 0 {
+            let read = self
+                .reader
+                .read(&mut buf, self.pos)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{:?}", e)))?;
+            buf.truncate(read);
+        }
+        self.buf_start = self.pos;
+        self.buf = buf;
+
+        Ok(())
+    }
+}
+
+impl Read for BlobSeekReader {
+    fn read(&mut self, out: &mut [u8]) -> IoResult<usize> {
+        if self.pos >= self.blob_size {
+            return Ok(0);
+        }
+        if self.pos < self.buf_start || self.pos >= self.buf_end() {
+            self.fill_buffer()?;
+        }
+        if self.buf.is_empty() {
+            return Ok(0);
+        }
+
+        let offset_in_buf = (self.pos - self.buf_start) as usize;
+        let available = &self.buf[offset_in_buf..];
+        let n = min(available.len(), out.len());
+        out[..n].copy_from_slice(&available[..n]);
+        self.pos += n as u64;
+
+        Ok(n)
+    }
+}
+
+impl Seek for BlobSeekReader {
+    fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(p) => p as i64,
+            SeekFrom::End(p) => self.blob_size as i64 + p,
+            SeekFrom::Current(p) => self.pos as i64 + p,
+        };
+        if new_pos < 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "seek position is negative",
+            ));
+        }
+
+        // Clamp to the blob size: seeking past the end is allowed by the `Seek` contract, but
+        // subsequent reads must behave as if at EOF.
+        let new_pos = min(new_pos as u64, self.blob_size);
+
+        // Only keep the buffer if the new position still falls inside it; otherwise there's no
+        // overlap between the buffered bytes and the new position, so there's nothing to retain.
+        if new_pos < self.buf_start || new_pos > self.buf_end() {
+            self.buf.clear();
+            self.buf_start = new_pos;
+        }
+        self.pos = new_pos;
+
+        Ok(self.pos)
+    }
+}
+
+impl dyn BlobReader {
+    /// Wrap this `BlobReader` in a `std::io::{Read, Seek}` adapter for sequential consumers.
+    pub fn into_seekable(self: Arc<Self>) -> IoResult<BlobSeekReader> {
+        BlobSeekReader::new(self)
+    }
+}